@@ -1,21 +1,21 @@
-﻿use crate::cli::repositories::Repositories;
+﻿use crate::cli::error::{Error, Result};
+use crate::cli::repositories::{clone_repos, CloneOptions, Repositories};
+
+pub fn execute(
+    registry: Option<&String>,
+    modules: &[String],
+    options: &CloneOptions) -> Result<()> {
 
-pub fn execute(index: Option<&String>) -> Result<(), Box<dyn std::error::Error>> {
     println!("\nClone repo");
-    let framework = Repositories::new();
-    match index {
-        Some(idx) => {
-            let idx_num = idx.parse::<usize>()?;
-            framework.clone_by_index(idx_num)?;
-        },
-        None => {
-            let results = framework.clone_all();
-            for result in results {
-                if let Err(e) = result {
-                    return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)));
-                }
-            }
-        },
+    let framework = Repositories::load(registry)?;
+    let results = if modules.is_empty() {
+        framework.clone_all(options)
+    } else {
+        clone_repos(&framework.resolve(modules)?, options)
+    };
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    if failed > 0 {
+        return Err(Error::Git(format!("{} of {} modules failed to clone", failed, results.len())));
     }
     Ok(())
 }
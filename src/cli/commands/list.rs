@@ -1,9 +1,15 @@
-﻿use crate::cli::repositories::Repositories;
-use std::error;
+﻿use crate::cli::error::Result;
+use crate::cli::output::{Modules, OutputFormat};
+use crate::cli::repositories::Repositories;
 
-pub fn execute() -> Result<(), Box<dyn error::Error>> {
-    let framework = Repositories::new();
-    eprintln!("\n");
-    framework.list();
+pub fn execute(registry: Option<&String>, format: OutputFormat) -> Result<()> {
+    let framework = Repositories::load(registry)?;
+    if format == OutputFormat::Table {
+        println!("\n");
+        framework.list();
+    } else {
+        let modules: Vec<_> = framework.all().into_iter().cloned().collect();
+        format.print(&Modules { modules: &modules })?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
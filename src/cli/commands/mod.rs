@@ -1,15 +1,34 @@
-﻿use clap::{Arg, ArgMatches, Command};
+﻿use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use clap::parser::ValueSource;
 use colored::Colorize;
+use crate::cli::config::{is_valid_prefix, is_valid_scope};
+use crate::cli::unity_editor::is_valid_unity_version;
+use crate::cli::error::{Error, Result};
+use crate::cli::meta::{GuidMode, GUID_MODES};
+use crate::cli::packages_manifest::{DependencySource, DEPENDENCY_SOURCES};
+use crate::cli::output::OutputFormat;
+use crate::cli::project::Answers;
+use new::NewOptions;
+use crate::cli::repositories::CloneOptions;
+use crate::cli::upm::{ReferenceMode, Sample, TestAssemblies, REFERENCE_MODES, TEST_ASSEMBLIES};
+use std::path::{Path, PathBuf};
 
 mod clone;
 mod list;
 mod new;
+mod registry;
+mod unity;
+mod status;
+mod update;
 
-pub fn execute_list() {
-    match list::execute() {
-        Ok(_) => println!("\n"),
-        Err(_) => println!("{}", "List error\n".red())
+pub fn execute_list(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    let format = OutputFormat::from_args(args);
+    list::execute(registry, format)?;
+    if format == OutputFormat::Table {
+        println!("\n");
     }
+    Ok(())
 }
 
 pub fn build_list() -> Command {
@@ -17,37 +36,438 @@ pub fn build_list() -> Command {
         .about("Display the list of e314 modules")
 }
 
-pub fn execute_clone(args: &ArgMatches) {
-    let idx = args.get_one::<String>("index");
-    match idx {
-        Some(index) => match clone::execute(Some(index)) {
-            Ok(_) => println!("\n{}", "Clone success\n".green()),
-            Err(_) => println!("{}\n", "Clone error\n".red())
-        },
-        None => match clone::execute(None) {
-            Ok(_) => println!("\n{}", "Clone success\n".green()),
-            Err(_) => println!("{}", "Clone error\n".red())
-        }
-    }
+pub fn execute_clone(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    let modules: Vec<String> = args.get_many::<String>("modules")
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default();
+    let options = CloneOptions {
+        git_ref: args.get_one::<String>("ref").cloned(),
+        depth: args.get_one::<u32>("depth").copied(),
+        jobs: *args.get_one::<usize>("jobs").unwrap(),
+    };
+    clone::execute(registry, &modules, &options)?;
+    println!("\n{}", "Clone success\n".green());
+    Ok(())
 }
 
 pub fn build_clone() -> Command {
     Command::new("clone")
-        .about("Clone a repository by index")
+        .about("Clone repositories by index, name or glob pattern")
+        .arg(
+            Arg::new("modules")
+                .help("Indices, names or glob patterns of the modules (optional - clones all if not specified)")
+                .num_args(1..)
+                .index(1))
+        .arg(
+            Arg::new("ref")
+                .long("ref")
+                .value_name("REF")
+                .help("Branch, tag or commit to check out instead of the registered version")
+                .requires("modules"))
+        .arg(
+            Arg::new("depth")
+                .long("depth")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("Create a shallow clone with N commits of history"))
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("4")
+                .help("Number of repositories cloned at the same time"))
+}
+
+pub fn execute_update(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    let modules: Vec<String> = args.get_many::<String>("modules")
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default();
+    let dir = args.get_one::<String>("dir").unwrap();
+    update::execute(registry, &modules, dir)?;
+    println!("\n{}", "Update success\n".green());
+    Ok(())
+}
+
+pub fn build_update() -> Command {
+    Command::new("update")
+        .visible_alias("pull")
+        .about("Update cloned repositories to the registered versions")
         .arg(
-            Arg::new("index")
-                .help("The index of the module (optional - clones all if not specified)")
+            Arg::new("modules")
+                .help("Indices, names or glob patterns of the modules (optional - updates all if not specified)")
+                .num_args(1..)
                 .index(1))
+        .arg(
+            Arg::new("dir")
+                .long("dir")
+                .value_name("PATH")
+                .default_value(".")
+                .help("Directory containing the cloned modules"))
 }
 
-pub fn execute_new() {
-    match new::execute() {
-        Ok(_) => {},
-        Err(_) => println!("{}", "Init error\n".red())
+pub fn execute_status(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    let dir = args.get_one::<String>("dir").unwrap();
+    // clap only sees the conflict when --format comes after the subcommand.
+    let format_given = args.value_source("format") == Some(ValueSource::CommandLine);
+    let format = match args.get_flag("json") {
+        true if format_given => return Err(Error::InvalidInput(
+            String::from("--json can't be used with --format, use --format json"))),
+        true => OutputFormat::Json,
+        false => OutputFormat::from_args(args),
+    };
+    status::execute(registry, dir, format)?;
+    if format == OutputFormat::Table {
+        println!("\n");
     }
+    Ok(())
+}
+
+pub fn build_status() -> Command {
+    Command::new("status")
+        .about("Display the state of the cloned e314 modules")
+        .arg(
+            Arg::new("dir")
+                .long("dir")
+                .value_name("PATH")
+                .default_value(".")
+                .help("Directory containing the cloned modules"))
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("format")
+                .help("Same as --format json"))
+}
+
+pub fn execute_new(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    let answers = Answers {
+        name: args.get_one::<String>("name").cloned(),
+        version: args.get_one::<String>("version").cloned(),
+        description: args.get_one::<String>("description").cloned(),
+        author_name: args.get_one::<String>("author-name").cloned(),
+        author_email: args.get_one::<String>("author-email").cloned(),
+        dependencies: args.get_many::<String>("deps").map(|values| values.cloned().collect()),
+        author_url: args.get_one::<String>("author-url").cloned(),
+        keywords: args.get_many::<String>("keywords").map(|values| values.cloned().collect()),
+        license: args.get_one::<String>("license").cloned(),
+        repository: args.get_one::<String>("repository").cloned(),
+        min_unity_version: args.get_one::<String>("min-unity-version").cloned(),
+        documentation_url: args.get_one::<String>("documentation-url").cloned(),
+        changelog_url: args.get_one::<String>("changelog-url").cloned(),
+        licenses_url: args.get_one::<String>("licenses-url").cloned(),
+        samples: args.get_many::<String>("samples")
+            .map(|folders| folders.filter(|folder| !folder.is_empty()).map(|folder| Sample::in_folder(folder)).collect()),
+    };
+    let answers_file = args.get_one::<String>("answers");
+    let options = NewOptions {
+        scope: args.get_one::<String>("scope").cloned(),
+        prefix: args.get_one::<String>("prefix").cloned(),
+        template: args.get_one::<String>("template").cloned(),
+        guids: args.get_one::<String>("guids").and_then(|guids| GuidMode::parse(guids)),
+        asmdef_references: args.get_one::<String>("asmdef-references")
+            .and_then(|references| ReferenceMode::parse(references)),
+        tests: args.get_one::<String>("tests").and_then(|tests| TestAssemblies::parse(tests)),
+        dependency_source: args.get_one::<String>("dependency-source")
+            .and_then(|source| DependencySource::parse(source)),
+        unity_version: args.get_one::<String>("unity-version").cloned(),
+        output: PathBuf::from(args.get_one::<String>("output").unwrap()),
+        dry_run: args.get_flag("dry-run"),
+        offline: args.get_flag("offline"),
+        contents: args.get_flag("contents"),
+        diff: args.get_flag("diff"),
+        force: args.get_flag("force"),
+    };
+    new::execute(registry, answers, answers_file, &options)
 }
 
 pub fn build_new() -> Command {
     Command::new("new")
         .about("Create a new project")
+        .after_help("Values that are not given are asked for when stdin is a terminal.")
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .help("Name of the module without the scope, e.g. Protect"))
+        .arg(
+            Arg::new("version")
+                .long("version")
+                .help("Version in the X.Y.Z format"))
+        .arg(
+            Arg::new("description")
+                .long("description")
+                .help("Description of the module"))
+        .arg(
+            Arg::new("author-name")
+                .long("author-name")
+                .help("Name of the author"))
+        .arg(
+            Arg::new("author-email")
+                .long("author-email")
+                .help("Email of the author"))
+        .arg(
+            Arg::new("deps")
+                .long("deps")
+                .value_delimiter(',')
+                .help("Comma-separated indices, names or glob patterns of the dependencies; --deps= for none"))
+        .arg(
+            Arg::new("author-url")
+                .long("author-url")
+                .value_name("URL")
+                .help("Website of the author"))
+        .arg(
+            Arg::new("keywords")
+                .long("keywords")
+                .value_delimiter(',')
+                .help("Comma-separated keywords of the package"))
+        .arg(
+            Arg::new("license")
+                .long("license")
+                .value_name("SPDX")
+                .help("License of the package [default: MIT]"))
+        .arg(
+            Arg::new("repository")
+                .long("repository")
+                .value_name("URL")
+                .help("Git URL of the repository of the module"))
+        .arg(
+            Arg::new("min-unity-version")
+                .long("min-unity-version")
+                .value_name("VERSION")
+                .help("Oldest Unity version the package supports, e.g. 2022.3 [default: that of the editor]"))
+        .arg(
+            Arg::new("documentation-url")
+                .long("documentation-url")
+                .value_name("URL")
+                .help("Documentation of the package [default: its README.md in an https repository]"))
+        .arg(
+            Arg::new("changelog-url")
+                .long("changelog-url")
+                .value_name("URL")
+                .help("Changelog of the package [default: its CHANGELOG.md in an https repository]"))
+        .arg(
+            Arg::new("licenses-url")
+                .long("licenses-url")
+                .value_name("URL")
+                .help("License of the package [default: its LICENSE.md in an https repository]"))
+        .arg(
+            Arg::new("samples")
+                .long("samples")
+                .value_name("FOLDERS")
+                .value_delimiter(',')
+                .help("Comma-separated folders of the samples in Samples~; --samples= for none"))
+        .arg(
+            Arg::new("answers")
+                .long("answers")
+                .value_name("PATH")
+                .help("YAML or JSON file with the answers; flags take precedence over it"))
+        .arg(
+            Arg::new("scope")
+                .long("scope")
+                .value_name("SCOPE")
+                .value_parser(|scope: &str| match is_valid_scope(scope) {
+                    true => Ok(scope.to_string()),
+                    false => Err("expected a lowercase reverse domain, e.g. com.company"),
+                })
+                .help("Reverse-domain scope of the package name [default: com.e314]"))
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .value_name("PREFIX")
+                .value_parser(|prefix: &str| match is_valid_prefix(prefix) {
+                    true => Ok(prefix.to_string()),
+                    false => Err("expected a C# namespace, e.g. Company"),
+                })
+                .help("Prefix of the display name, assemblies and namespaces [default: E314]"))
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .value_name("DIR|NAME")
+                .help("Template pack directory, or the name of one in ~/.config/e314/templates"))
+        .arg(
+            Arg::new("guids")
+                .long("guids")
+                .value_name("MODE")
+                .value_parser(GUID_MODES)
+                .help("GUIDs of the .meta files: stable (derived from the paths) or random [default: stable]"))
+        .arg(
+            Arg::new("asmdef-references")
+                .long("asmdef-references")
+                .value_name("MODE")
+                .value_parser(REFERENCE_MODES)
+                .help("How assembly definitions reference other assemblies: name or guid [default: name]"))
+        .arg(
+            Arg::new("tests")
+                .long("tests")
+                .value_name("KIND")
+                .value_parser(TEST_ASSEMBLIES)
+                .help("Test assemblies to generate: editor, runtime, both or none [default: both]"))
+        .arg(
+            Arg::new("dependency-source")
+                .long("dependency-source")
+                .value_name("SOURCE")
+                .value_parser(DEPENDENCY_SOURCES)
+                .help("Where the Unity project installs the dependencies from: git or registry [default: git]"))
+        .arg(
+            Arg::new("unity-version")
+                .long("unity-version")
+                .value_name("VERSION")
+                .value_parser(|version: &str| match is_valid_unity_version(version) {
+                    true => Ok(version.to_string()),
+                    false => Err("expected a Unity editor version, e.g. 6000.1.4f1"),
+                })
+                .help("Unity editor version of the project [default: the newest installed with Unity Hub]"))
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("DIR")
+                .default_value(".")
+                .help("Directory to create the module in"))
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("Print the tree of files that would be generated without writing them"))
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .action(ArgAction::SetTrue)
+                .help("Resolve the lock file from the registry cache only, without fetching or cloning packages"))
+        .arg(
+            Arg::new("contents")
+                .long("contents")
+                .action(ArgAction::SetTrue)
+                .requires("dry-run")
+                .help("With --dry-run, print the content of every file"))
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .action(ArgAction::SetTrue)
+                .requires("dry-run")
+                .help("With --dry-run, print the diff against files that already exist"))
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite the generated files of an existing module directory"))
+}
+
+pub fn execute_registry(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    match args.subcommand() {
+        Some(("add", sub_args)) => {
+            let url = sub_args.get_one::<String>("url").unwrap();
+            registry::execute_add(registry, url)
+        },
+        Some(("remove", sub_args)) => {
+            let name = sub_args.get_one::<String>("name").unwrap();
+            registry::execute_remove(registry, name)
+        },
+        Some(("edit", sub_args)) => {
+            let name = sub_args.get_one::<String>("name").unwrap();
+            let version = sub_args.get_one::<String>("version");
+            let url = sub_args.get_one::<String>("url");
+            registry::execute_edit(registry, name, version, url)
+        },
+        _ => unreachable!("registry requires a subcommand"),
+    }?;
+    println!("\n");
+    Ok(())
+}
+
+pub fn build_registry() -> Command {
+    Command::new("registry")
+        .about("Manage the list of e314 modules")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a module by the url of its git repository")
+                .arg(
+                    Arg::new("url")
+                        .help("The git url of the module")
+                        .required(true)
+                        .index(1)))
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a module by name")
+                .arg(
+                    Arg::new("name")
+                        .help("The name of the module")
+                        .required(true)
+                        .index(1)))
+        .subcommand(
+            Command::new("edit")
+                .about("Change the version or url of a module")
+                .arg(
+                    Arg::new("name")
+                        .help("The name of the module")
+                        .required(true)
+                        .index(1))
+                .arg(
+                    Arg::new("version")
+                        .long("version")
+                        .help("The new version of the module"))
+                .arg(
+                    Arg::new("url")
+                        .long("url")
+                        .help("The new git url of the module"))
+                .group(
+                    ArgGroup::new("changes")
+                        .args(["version", "url"])
+                        .required(true)
+                        .multiple(true)))
+}
+
+pub fn execute_unity(args: &ArgMatches) -> Result<()> {
+    let registry = args.get_one::<String>("registry");
+    match args.subcommand() {
+        Some(("add", sub_args)) => {
+            let project = Path::new(sub_args.get_one::<String>("project").unwrap());
+            let package = sub_args.get_one::<String>("package").unwrap();
+            unity::execute_add(registry, project, package)
+        },
+        Some(("remove", sub_args)) => {
+            let project = Path::new(sub_args.get_one::<String>("project").unwrap());
+            let name = sub_args.get_one::<String>("name").unwrap();
+            unity::execute_remove(project, name)
+        },
+        _ => unreachable!("unity requires a subcommand"),
+    }?;
+    println!("\n");
+    Ok(())
+}
+
+pub fn build_unity() -> Command {
+    let project = Arg::new("project")
+        .long("project")
+        .value_name("DIR")
+        .default_value(".")
+        .help("The Unity project, the folder holding Packages/manifest.json");
+    Command::new("unity")
+        .about("Edit the packages of a Unity project")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a package, or change its version")
+                .arg(
+                    Arg::new("package")
+                        .help("The package as <name> for an e314 module or <name>@<version|git url|file:path>")
+                        .required(true)
+                        .index(1))
+                .arg(project.clone()))
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a package")
+                .arg(
+                    Arg::new("name")
+                        .help("The name of the package")
+                        .required(true)
+                        .index(1))
+                .arg(project))
 }
\ No newline at end of file
@@ -1,9 +1,93 @@
-﻿use crate::cli::project::Project;
+﻿use crate::cli::config::Config;
+use crate::cli::error::Result;
+use crate::cli::meta::GuidMode;
+use crate::cli::packages_manifest::DependencySource;
+use crate::cli::project::{Answers, Project};
+use crate::cli::repositories::Repositories;
+use crate::cli::template::Pack;
+use crate::cli::upm::{ReferenceMode, TestAssemblies};
+use colored::Colorize;
+use std::path::PathBuf;
 
-pub fn execute() -> Result<(), Box<dyn std::error::Error>> {
-    let mut project = Project::new();
-    project.configure();
-    project.create()?;
+pub struct NewOptions {
+    /// Overrides the configured package scope.
+    pub scope: Option<String>,
+    /// Overrides the configured display name prefix.
+    pub prefix: Option<String>,
+    /// Overrides the configured template pack.
+    pub template: Option<String>,
+    /// Overrides the configured GUID mode of the `.meta` files.
+    pub guids: Option<GuidMode>,
+    /// Overrides the configured way assembly definitions reference other assemblies.
+    pub asmdef_references: Option<ReferenceMode>,
+    /// Overrides the configured test assemblies.
+    pub tests: Option<TestAssemblies>,
+    /// Overrides the configured source of the dependencies in the Unity project.
+    pub dependency_source: Option<DependencySource>,
+    /// Overrides the configured Unity editor version.
+    pub unity_version: Option<String>,
+    /// Directory the module directory is created in.
+    pub output: PathBuf,
+    /// Print the planned files instead of writing them.
+    pub dry_run: bool,
+    /// Resolve the lock file from the cached registry documents only.
+    pub offline: bool,
+    /// With `dry_run`, print the content of every file.
+    pub contents: bool,
+    /// With `dry_run`, print the diff of files that already exist with other content.
+    pub diff: bool,
+    /// Write over an existing module directory instead of refusing to.
+    pub force: bool,
+}
+
+pub fn execute(
+    registry: Option<&String>,
+    answers: Answers,
+    answers_file: Option<&String>,
+    options: &NewOptions) -> Result<()> {
+
+    let repositories = Repositories::load(registry)?;
+    let answers = match answers_file {
+        Some(path) => answers.or(Answers::load(path)?),
+        None => answers,
+    };
+    let mut config = Config::load()?;
+    if let Some(scope) = &options.scope {
+        config.scope = scope.clone();
+    }
+    if let Some(prefix) = &options.prefix {
+        config.prefix = prefix.clone();
+    }
+    if let Some(template) = &options.template {
+        config.template = Some(template.clone());
+    }
+    if let Some(guids) = options.guids {
+        config.guids = guids;
+    }
+    if let Some(references) = options.asmdef_references {
+        config.asmdef_references = references;
+    }
+    if let Some(tests) = options.tests {
+        config.tests = tests;
+    }
+    if let Some(dependency_source) = options.dependency_source {
+        config.dependency_source = dependency_source;
+    }
+    if let Some(unity_version) = &options.unity_version {
+        config.unity.version = Some(unity_version.clone());
+    }
+    let pack = Pack::load(config.template.as_deref())?;
+    let mut project = Project::new(repositories, config);
+    project.configure(answers)?;
+    // A dry run neither fetches nor clones packages to resolve the lock file.
+    let plan = project.plan(&options.output, &pack, options.dry_run || options.offline)?;
+
+    if options.dry_run {
+        plan.print(options.contents, options.diff);
+        println!("\n{}", "Dry run - nothing was written\n".yellow());
+    } else {
+        plan.apply(options.force)?;
+    }
     Ok(())
 }
 
@@ -0,0 +1,53 @@
+﻿use crate::cli::error::{Error, Result};
+use crate::cli::project::is_valid_semantic_version;
+use crate::cli::repositories::{fetch_repo_info, Repositories};
+use colored::Colorize;
+
+pub fn execute_add(registry: Option<&String>, url: &str) -> Result<()> {
+    let mut repositories = Repositories::load(registry)?;
+    println!("\nAdd module");
+    let repo = fetch_repo_info(url)?;
+    repositories.add(repo.clone())?;
+    let path = repositories.save()?;
+    println!("  {} {} - {} - {}", "✓".green(), repo.name, repo.version, repo.url);
+    println!("  {} Registry: {}", "✓".green(), path.display().to_string().blue());
+    Ok(())
+}
+
+pub fn execute_remove(registry: Option<&String>, name: &str) -> Result<()> {
+    let mut repositories = Repositories::load(registry)?;
+    println!("\nRemove module");
+    let repo = repositories.remove(name)?;
+    let path = repositories.save()?;
+    println!("  {} {}", "✓".green(), repo.name);
+    println!("  {} Registry: {}", "✓".green(), path.display().to_string().blue());
+    Ok(())
+}
+
+pub fn execute_edit(
+    registry: Option<&String>,
+    name: &str,
+    version: Option<&String>,
+    url: Option<&String>) -> Result<()> {
+
+    let mut repositories = Repositories::load(registry)?;
+    println!("\nEdit module");
+
+    if let Some(version) = version.filter(|version| !is_valid_semantic_version(version)) {
+        return Err(Error::InvalidInput(format!("Version: {} - invalid format", version)));
+    }
+
+    let repo = repositories.get_repo_by_name_mut(name)?;
+    if let Some(version) = version {
+        repo.version = version.clone();
+    }
+    if let Some(url) = url {
+        repo.url = url.clone();
+    }
+    let repo = repo.clone();
+
+    let path = repositories.save()?;
+    println!("  {} {} - {} - {}", "✓".green(), repo.name, repo.version, repo.url);
+    println!("  {} Registry: {}", "✓".green(), path.display().to_string().blue());
+    Ok(())
+}
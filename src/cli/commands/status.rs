@@ -0,0 +1,84 @@
+﻿use crate::cli::error::{Error, Result};
+use crate::cli::output::{Modules, OutputFormat};
+use crate::cli::repositories::{indent, Repositories};
+use crate::cli::workspace::{self, ModuleStatus};
+use colored::Colorize;
+use std::path::Path;
+
+pub fn execute(registry: Option<&String>, dir: &str, format: OutputFormat) -> Result<()> {
+    let framework = Repositories::load(registry)?;
+    let statuses = framework.all()
+        .into_iter()
+        .map(|repo| workspace::status(repo, Path::new(dir)))
+        .collect::<Vec<ModuleStatus>>();
+
+    if format == OutputFormat::Table {
+        println!("\n");
+        print_table(&statuses);
+    } else {
+        format.print(&Modules { modules: &statuses })?;
+    }
+
+    let failed = statuses.iter().filter(|status| status.error.is_some()).count();
+    if failed > 0 {
+        return Err(Error::Git(format!("{} of {} modules failed to read", failed, statuses.len())));
+    }
+    Ok(())
+}
+
+fn print_table(statuses: &[ModuleStatus]) {
+    let rows: Vec<[String; 6]> = statuses.iter()
+        .map(|status| {
+            // Without a clone that could be read there is no ref, so its column tells why.
+            if !status.cloned || status.error.is_some() {
+                let state = if status.cloned { "failed" } else { "not cloned" };
+                return [status.name.clone(), String::from(state), status.registered_version.clone(),
+                        String::from("-"), String::from("-"), String::from("-")];
+            }
+            let sync = match (status.ahead, status.behind) {
+                (Some(ahead), Some(behind)) => format!("+{} -{}", ahead, behind),
+                _ => String::from("-"),
+            };
+            [
+                status.name.clone(),
+                status.git_ref.clone().unwrap_or_default(),
+                status.registered_version.clone(),
+                sync,
+                status.dirty_files.to_string(),
+                status.package_version.clone().unwrap_or_else(|| String::from("-")),
+            ]
+        })
+        .collect();
+
+    let header = ["Module", "Ref", "Version", "Ahead/Behind", "Dirty", "package.json"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    println!("Modules:");
+    println!("    {:<w0$}  {:<w1$}  {:<w2$}  {:<w3$}  {:<w4$}  {}", header[0], header[1], header[2],
+             header[3], header[4], header[5],
+             w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3], w4 = widths[4]);
+    for (status, row) in statuses.iter().zip(&rows) {
+        let mark = if !status.cloned {
+            "-".dimmed()
+        } else if status.error.is_none() && status.matches_version {
+            "✓".green()
+        } else {
+            "✗".red()
+        };
+        let dirty = if status.dirty_files > 0 {
+            format!("{:<w$}", row[4], w = widths[4]).yellow()
+        } else {
+            format!("{:<w$}", row[4], w = widths[4]).normal()
+        };
+        println!("  {} {:<w0$}  {:<w1$}  {:<w2$}  {:<w3$}  {}  {}", mark, row[0], row[1], row[2], row[3],
+                 dirty, row[5], w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3]);
+        if let Some(error) = &status.error {
+            println!("{}", indent(error));
+        }
+    }
+}
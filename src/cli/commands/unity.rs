@@ -0,0 +1,100 @@
+﻿use crate::cli::config::Config;
+use crate::cli::error::{Error, Result};
+use crate::cli::packages_lock::{PackagesLock, Resolver};
+use crate::cli::packages_manifest::PackagesManifest;
+use crate::cli::plan::Plan;
+use crate::cli::project::is_valid_semantic_version;
+use crate::cli::repositories::Repositories;
+use colored::Colorize;
+use std::path::Path;
+
+/// Adds `package`, `name` or `name@version`, to the manifest of the Unity project. E314
+/// modules are installed from the configured dependency source and made testable; other
+/// packages need a version, a git URL or a `file:` path.
+pub fn execute_add(registry: Option<&String>, project: &Path, package: &str) -> Result<()> {
+    let packages_path = project.join("Packages");
+    let manifest_path = packages_path.join("manifest.json");
+    let mut manifest = PackagesManifest::load(&manifest_path)?;
+    println!("\nAdd package");
+
+    let (name, version) = match package.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (package, None),
+    };
+    if name.is_empty() || version.is_some_and(str::is_empty) {
+        return Err(Error::InvalidInput(format!("Package: {} - expected <name>[@<version>]", package)));
+    }
+
+    // Modules are found the way `clone` finds them; anything else is a package name.
+    let repositories = Repositories::load(registry)?;
+    let module = match repositories.resolve_one(name) {
+        Ok(modules) if modules.len() == 1 => Some(modules[0]),
+        Ok(modules) => return Err(Error::InvalidInput(format!("Package: {} - matches several modules: {}", name,
+            modules.iter().map(|module| module.name.as_str()).collect::<Vec<_>>().join(", ")))),
+        Err(e @ Error::InvalidInput(_)) => return Err(e),
+        Err(_) => None,
+    }.filter(|_| version.is_none_or(is_valid_semantic_version));
+    let name = match (module, version) {
+        (Some(module), _) => {
+            let mut module = module.clone();
+            if let Some(version) = version {
+                module.version = version.to_string();
+            }
+            manifest.add_dependency(&module, Config::load()?.dependency_source)?;
+            module.name
+        },
+        (None, Some(version)) => {
+            manifest.set_dependency(name, version);
+            name.to_string()
+        },
+        (None, None) => return Err(Error::InvalidInput(
+            format!("Package: {} - not an E314 module, give its version as {}@<version>", name, name))),
+    };
+    let version = manifest.dependencies().into_iter()
+        .find(|(dependency, _)| *dependency == name)
+        .map(|(_, version)| version)
+        .unwrap_or_default();
+
+    manifest.save(&manifest_path)?;
+    println!("  {} {} - {}", "✓".green(), name, version);
+    println!("  {} Manifest: {}", "✓".green(), manifest_path.display().to_string().blue());
+    update_lock(&manifest, project, &packages_path)
+}
+
+pub fn execute_remove(project: &Path, name: &str) -> Result<()> {
+    let packages_path = project.join("Packages");
+    let manifest_path = packages_path.join("manifest.json");
+    let mut manifest = PackagesManifest::load(&manifest_path)?;
+    println!("\nRemove package");
+
+    let version = manifest.remove_dependency(name).ok_or_else(|| Error::InvalidInput(
+        format!("Package: {} - not in {}", name, manifest_path.display())))?;
+
+    manifest.save(&manifest_path)?;
+    println!("  {} {} - {}", "✓".green(), name, version);
+    println!("  {} Manifest: {}", "✓".green(), manifest_path.display().to_string().blue());
+    update_lock(&manifest, project, &packages_path)
+}
+
+/// Resolves the lock file again, keeping the packages that didn't change as they were.
+/// When that fails the lock file is left as it is, and Unity updates it.
+fn update_lock(manifest: &PackagesManifest, project: &Path, packages_path: &Path) -> Result<()> {
+    if !manifest.lock_file_enabled() {
+        return Ok(());
+    }
+    let lock_path = packages_path.join("packages-lock.json");
+    let previous = PackagesLock::load(&lock_path)?;
+    let resolved = Resolver::new(manifest.scoped_registries(), previous.as_ref(), false)
+        .resolve(manifest, &Plan::new(project), Path::new("Packages"));
+    let lock = match resolved {
+        Ok(lock) => lock,
+        Err(e) => {
+            println!("  {} Lock: {}", "!".yellow(),
+                     format!("not updated, Unity updates it: {}", e.message()).yellow());
+            return Ok(());
+        },
+    };
+    lock.save(&lock_path)?;
+    println!("  {} Lock: {}", "✓".green(), lock_path.display().to_string().blue());
+    Ok(())
+}
\ No newline at end of file
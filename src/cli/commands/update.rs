@@ -0,0 +1,42 @@
+﻿use crate::cli::error::{Error, Result};
+use crate::cli::repositories::{indent, Repositories};
+use crate::cli::workspace::{self, UpdateOutcome};
+use colored::Colorize;
+use std::path::Path;
+
+pub fn execute(registry: Option<&String>, modules: &[String], dir: &str) -> Result<()> {
+    println!("\nUpdate repo");
+    let framework = Repositories::load(registry)?;
+    let repos = if modules.is_empty() {
+        framework.all()
+    } else {
+        framework.resolve(modules)?
+    };
+
+    let clones = workspace::find_clones(&repos, Path::new(dir));
+    if clones.is_empty() {
+        return Err(Error::InvalidInput(format!("No cloned modules were found in '{}'", dir)));
+    }
+
+    let mut failed = 0;
+    for (repo, path) in &clones {
+        match workspace::update(repo, path) {
+            Ok(UpdateOutcome::Updated(git_ref)) => println!("  {} {} ({})", "✓".green(), repo.name, git_ref),
+            Ok(UpdateOutcome::UpToDate(git_ref)) => {
+                println!("  {} {} ({}) - up to date", "✓".green(), repo.name, git_ref)
+            },
+            Ok(UpdateOutcome::Dirty) => {
+                println!("  {} {} - {}", "!".yellow(), repo.name, "skipped, working tree has uncommitted changes".yellow())
+            },
+            Err(e) => {
+                failed += 1;
+                println!("  {} {}\n{}", "✗".red(), repo.name, indent(e.message()));
+            },
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Git(format!("{} of {} modules failed to update", failed, clones.len())));
+    }
+    Ok(())
+}
@@ -0,0 +1,370 @@
+﻿use crate::cli::error::{Error, Result};
+use crate::cli::meta::GuidMode;
+use crate::cli::packages_manifest::{DependencySource, ScopedRegistry};
+use crate::cli::unity_editor::is_valid_unity_version;
+use crate::cli::upm::{ReferenceMode, TestAssemblies};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const LOCAL_CONFIG_FILE: &str = "e314.yaml";
+const USER_CONFIG_FILE: &str = "config.yaml";
+
+/// Settings of the generators, read from `~/.config/e314/config.yaml` and overridden
+/// key by key by `./e314.yaml`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Reverse-domain scope of the package names, e.g. `com.e314`.
+    pub scope: String,
+    /// Prefix of the display names, assemblies and namespaces, e.g. `E314`.
+    pub prefix: String,
+    /// Template pack used instead of the built-in one, a directory or the name of one in
+    /// `~/.config/e314/templates`.
+    pub template: Option<String>,
+    /// How the GUIDs of the `.meta` files of the package are made.
+    pub guids: GuidMode,
+    /// How the assembly definitions of the package reference other assemblies.
+    pub asmdef_references: ReferenceMode,
+    /// Test assemblies of the package.
+    pub tests: TestAssemblies,
+    /// Where the Unity project installs the E314 dependencies from.
+    pub dependency_source: DependencySource,
+    pub layout: Layout,
+    pub unity: UnitySettings,
+}
+
+/// Additions to the `Packages/manifest.json` of the generated Unity project.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitySettings {
+    /// Editor version of the project, e.g. `6000.1.4f1`. The newest editor installed with
+    /// Unity Hub is used when it is not set.
+    pub version: Option<String>,
+    /// Registries the project resolves packages from, e.g. OpenUPM or a private one.
+    pub scoped_registries: Vec<ScopedRegistry>,
+    /// Packages whose tests the Test Runner shows, besides the module and its dependencies.
+    pub testables: Vec<String>,
+}
+
+/// Where the parts of a generated module are placed. Folder names may contain `{name}`
+/// and `{display_name}`, and `.` puts a part directly in the root.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layout {
+    pub root: RootNaming,
+    pub upm: String,
+    pub unity: String,
+    pub dotnet: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RootNaming {
+    /// `com.e314.foo`
+    #[default]
+    Name,
+    /// `E314.Foo`
+    DisplayName,
+}
+
+/// Folders of the parts of a module, relative to its root.
+#[derive(Debug)]
+pub struct ModulePaths {
+    pub root: PathBuf,
+    pub upm: PathBuf,
+    pub unity: PathBuf,
+    pub dotnet: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            scope: String::from("com.e314"),
+            prefix: String::from("E314"),
+            template: None,
+            guids: GuidMode::default(),
+            asmdef_references: ReferenceMode::default(),
+            tests: TestAssemblies::default(),
+            dependency_source: DependencySource::default(),
+            layout: Layout::default(),
+            unity: UnitySettings::default(),
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            root: RootNaming::Name,
+            upm: String::from("UPM"),
+            unity: String::from("{display_name}.Unity"),
+            dotnet: String::from("."),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let mut candidates = Vec::new();
+        if let Some(dir) = user_config_dir() {
+            candidates.push(dir.join(USER_CONFIG_FILE));
+        }
+        candidates.push(PathBuf::from(LOCAL_CONFIG_FILE));
+        Self::load_from(&candidates)
+    }
+
+    /// The settings of the existing files of `candidates`, each overriding the keys of the
+    /// ones before it.
+    fn load_from(candidates: &[PathBuf]) -> Result<Self> {
+        let mut merged = Value::Null;
+        for candidate in candidates.iter().filter(|path| path.is_file()) {
+            let content = fs::read_to_string(candidate)
+                .map_err(|e| Error::Config(format!("Config file '{}' - {}", candidate.display(), e)))?;
+            let value: Value = serde_yaml::from_str(&content).map_err(|e| Error::Config(
+                format!("Config file '{}' is malformed: {}", candidate.display(), e)))?;
+            merge(&mut merged, value);
+        }
+
+        if merged.is_null() {
+            return Ok(Config::default());
+        }
+        let config: Config = serde_yaml::from_value(merged)
+            .map_err(|e| Error::Config(format!("Config is malformed: {}", e)))?;
+        if !is_valid_scope(&config.scope) {
+            return Err(Error::Config(format!("Scope '{}' is not a lowercase reverse domain", config.scope)));
+        }
+        if !is_valid_prefix(&config.prefix) {
+            return Err(Error::Config(format!("Prefix '{}' is not a C# namespace", config.prefix)));
+        }
+        // Reports a layout leaving the module root before any question is asked.
+        config.layout.paths(Path::new(""), "", "")?;
+        if let Some(version) = config.unity.version.as_deref().filter(|version| !is_valid_unity_version(version)) {
+            return Err(Error::Config(format!("Unity version '{}' is not an editor version, e.g. 6000.1.4f1", version)));
+        }
+        for registry in &config.unity.scoped_registries {
+            registry.validate()?;
+        }
+        Ok(config)
+    }
+
+    /// The package name and the display name of a module, e.g. `com.e314.protect` and
+    /// `E314.Protect` for `Protect`.
+    pub fn names(&self, input: &str) -> (String, String) {
+        let name = format!("{}.{}", self.scope, input.to_lowercase());
+        let display_name = match self.prefix.is_empty() {
+            true => input.to_string(),
+            false => format!("{}.{}", self.prefix, input),
+        };
+        (name, display_name)
+    }
+}
+
+impl Layout {
+    /// Resolves the folders of the module under `output`.
+    pub fn paths(&self, output: &Path, name: &str, display_name: &str) -> Result<ModulePaths> {
+        let root = match self.root {
+            RootNaming::Name => name,
+            RootNaming::DisplayName => display_name,
+        };
+        let output: PathBuf = output.components().filter(|c| *c != Component::CurDir).collect();
+        Ok(ModulePaths {
+            root: output.join(root),
+            upm: part_path("upm", &self.upm, name, display_name)?,
+            unity: part_path("unity", &self.unity, name, display_name)?,
+            dotnet: part_path("dotnet", &self.dotnet, name, display_name)?,
+        })
+    }
+}
+
+/// A scope such as `com.company`: dot-separated lowercase words.
+pub fn is_valid_scope(scope: &str) -> bool {
+    let scope_regex = regex::Regex::new(r"^[a-z0-9_-]+(\.[a-z0-9_-]+)*$").unwrap();
+    scope_regex.is_match(scope)
+}
+
+/// A prefix such as `Company.Unity`: dot-separated C# identifiers, or nothing.
+pub fn is_valid_prefix(prefix: &str) -> bool {
+    let prefix_regex = regex::Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*(\.[A-Za-z_][A-Za-z0-9_]*)*)?$").unwrap();
+    prefix_regex.is_match(prefix)
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+pub fn user_config_dir() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".config").join("e314"))
+}
+
+/// `$XDG_CACHE_HOME/e314`, or `~/.cache/e314`.
+pub fn user_cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".cache")))
+        .map(|dir| dir.join("e314"))
+}
+
+/// Overrides the keys of `base` with the ones of `value`, recursing into mappings.
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Mapping(base), Value::Mapping(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    },
+                }
+            }
+        },
+        (base, value) => *base = value,
+    }
+}
+
+/// The folder of a part, which has to stay inside the root of the module.
+fn part_path(part: &str, pattern: &str, name: &str, display_name: &str) -> Result<PathBuf> {
+    let path = pattern
+        .replace("{name}", name)
+        .replace("{display_name}", display_name);
+    let mut result = PathBuf::new();
+    for component in Path::new(&path).components() {
+        match component {
+            Component::Normal(part) => result.push(part),
+            Component::CurDir => {},
+            _ => return Err(Error::Config(format!(
+                "Layout folder of {} '{}' must be relative to the module root", part, pattern))),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::plan::link;
+    use crate::cli::test_fixtures::TempDir;
+
+    fn layout(yaml: &str) -> Layout {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn paths(layout: &Layout) -> Result<ModulePaths> {
+        layout.paths(Path::new("./out"), "com.e314.foo", "E314.Foo")
+    }
+
+    #[test]
+    fn the_default_layout_puts_the_parts_in_folders_of_the_root_named_after_the_package() {
+        let paths = paths(&Layout::default()).unwrap();
+        assert_eq!(paths.root, Path::new("out/com.e314.foo"));
+        assert_eq!(paths.upm, Path::new("UPM"));
+        assert_eq!(paths.unity, Path::new("E314.Foo.Unity"));
+        assert_eq!(paths.dotnet, Path::new(""));
+        assert_eq!(link(&paths.unity.join("Packages"), &paths.upm), "../../UPM");
+    }
+
+    #[test]
+    fn a_configured_layout_names_the_root_and_the_folders() {
+        let layout = layout("root: display_name\nupm: packages/{name}\nunity: ./unity\ndotnet: src/{display_name}\n");
+        let paths = paths(&layout).unwrap();
+        assert_eq!(paths.root, Path::new("out/E314.Foo"));
+        assert_eq!(paths.upm, Path::new("packages/com.e314.foo"));
+        assert_eq!(paths.unity, Path::new("unity"));
+        assert_eq!(paths.dotnet, Path::new("src/E314.Foo"));
+        assert_eq!(link(&paths.unity.join("Packages"), &paths.upm), "../../packages/com.e314.foo");
+        assert_eq!(link(&paths.dotnet, Path::new("")), "../..");
+    }
+
+    #[test]
+    fn a_part_in_the_root_links_to_itself() {
+        let paths = paths(&layout("upm: .\nunity: .\n")).unwrap();
+        assert_eq!(paths.upm, Path::new(""));
+        assert_eq!(link(&paths.unity, &paths.upm), ".");
+        assert_eq!(link(&paths.unity.join("Packages"), &paths.upm), "..");
+    }
+
+    #[test]
+    fn rejects_folders_leaving_the_module_root() {
+        for pattern in ["..", "../UPM", "UPM/../../UPM", "/tmp/UPM", "{name}/../.."] {
+            let layout = Layout { upm: pattern.to_string(), ..Layout::default() };
+            assert!(matches!(paths(&layout), Err(Error::Config(message)) if message.contains("upm")),
+                    "{} was accepted", pattern);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_layout_keys() {
+        assert!(serde_yaml::from_str::<Layout>("upm: UPM\ntests: Tests\n").is_err());
+    }
+
+    fn config(yaml: &str) -> Result<Config> {
+        let dir = TempDir::new("config");
+        Config::load_from(&[dir.write("e314.yaml", yaml)])
+    }
+
+    #[test]
+    fn names_the_package_after_the_scope_and_the_display_name_after_the_prefix() {
+        let config = Config::default();
+        assert_eq!(config.names("Protect"), (String::from("com.e314.protect"), String::from("E314.Protect")));
+
+        let config = Config { scope: String::from("com.company.unity"), prefix: String::from("Company.Unity"),
+            ..Config::default() };
+        assert_eq!(config.names("EventBus"),
+                   (String::from("com.company.unity.eventbus"), String::from("Company.Unity.EventBus")));
+
+        let config = Config { prefix: String::new(), ..Config::default() };
+        assert_eq!(config.names("Protect").1, "Protect");
+    }
+
+    #[test]
+    fn validates_scopes_and_prefixes() {
+        for scope in ["com.e314", "io.github.user-name", "com.company_1"] {
+            assert!(is_valid_scope(scope), "{}", scope);
+        }
+        for scope in ["", "Com.E314", "com..e314", ".com", "com.e314.", "com e314"] {
+            assert!(!is_valid_scope(scope), "{}", scope);
+        }
+        for prefix in ["", "E314", "Company.Unity", "_Internal"] {
+            assert!(is_valid_prefix(prefix), "{}", prefix);
+        }
+        for prefix in ["1Company", "Company.", "Company..Unity", "Company-Unity"] {
+            assert!(!is_valid_prefix(prefix), "{}", prefix);
+        }
+    }
+
+    #[test]
+    fn rejects_an_invalid_scope_or_prefix_in_a_config_file() {
+        assert!(matches!(config("scope: Com.E314\n"), Err(Error::Config(message)) if message.contains("Com.E314")));
+        assert!(matches!(config("prefix: 1Company\n"), Err(Error::Config(message)) if message.contains("1Company")));
+        assert!(matches!(config("layout:\n  upm: ../UPM\n"), Err(Error::Config(_))));
+        assert!(matches!(config("scope: [\n"), Err(Error::Config(message)) if message.contains("is malformed")));
+    }
+
+    #[test]
+    fn the_local_config_overrides_the_user_config_key_by_key() {
+        let dir = TempDir::new("config-merge");
+        let user = dir.write("config.yaml", "scope: com.company\nprefix: Company\nlayout:\n  upm: Package\n  unity: Unity\n");
+        let local = dir.write("e314.yaml", "prefix: Company.Tools\nlayout:\n  unity: Sandbox\n");
+        let missing = dir.path().join("missing.yaml");
+
+        let config = Config::load_from(&[user, missing, local]).unwrap();
+
+        assert_eq!(config.scope, "com.company");
+        assert_eq!(config.prefix, "Company.Tools");
+        assert_eq!(config.layout.upm, "Package");
+        assert_eq!(config.layout.unity, "Sandbox");
+        assert_eq!(config.layout.dotnet, ".");
+    }
+
+    #[test]
+    fn no_config_file_gives_the_defaults() {
+        let dir = TempDir::new("config-none");
+        let config = Config::load_from(&[dir.path().join("config.yaml"), dir.path().join("e314.yaml")]).unwrap();
+        assert_eq!((config.scope.as_str(), config.prefix.as_str()), ("com.e314", "E314"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,67 @@
+﻿use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A git command failed or could not be started.
+    Git(String),
+    Io(String),
+    /// A value given by the user is not valid.
+    InvalidInput(String),
+    /// The registry could not be read or written, or has no such module.
+    Registry(String),
+    /// A file could not be generated.
+    Template(String),
+    /// The configuration could not be read or has invalid settings.
+    Config(String),
+}
+
+impl Error {
+    /// The process exit code of the error category. 1 is left for unexpected failures
+    /// and 2 is also used by clap for invalid arguments.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidInput(_) => 2,
+            Error::Io(_) => 3,
+            Error::Git(_) => 4,
+            Error::Registry(_) => 5,
+            Error::Template(_) => 6,
+            Error::Config(_) => 7,
+        }
+    }
+
+    /// The error without its category, for the per-module lines of the commands.
+    pub fn message(&self) -> &str {
+        match self {
+            Error::Git(message)
+            | Error::Io(message)
+            | Error::InvalidInput(message)
+            | Error::Registry(message)
+            | Error::Template(message)
+            | Error::Config(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Git(message) => write!(f, "Git error - {}", message),
+            Error::Io(message) => write!(f, "IO error - {}", message),
+            Error::InvalidInput(message) => write!(f, "Invalid input - {}", message),
+            Error::Registry(message) => write!(f, "Registry error - {}", message),
+            Error::Template(message) => write!(f, "Template error - {}", message),
+            Error::Config(message) => write!(f, "Config error - {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error.to_string())
+    }
+}
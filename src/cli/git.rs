@@ -0,0 +1,38 @@
+﻿use crate::cli::error::{Error, Result};
+use std::path::Path;
+use std::process::{Command, Output};
+
+pub fn run(dir: Option<&Path>, args: &[&str]) -> Result<Output> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    command.args(args)
+        .output()
+        .map_err(|e| Error::Git(format!("Error running git: {}", e)))
+}
+
+/// Returns the full names of the remote refs, e.g. `refs/heads/master` or `refs/tags/1.1.2`.
+pub fn ls_remote(url: &str, args: &[&str]) -> Result<Vec<String>> {
+    let output = run(None, &[&["ls-remote"], args, &[url]].concat())?;
+    if !output.status.success() {
+        return Err(Error::Git(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(String::from)
+        .collect())
+}
+
+
+/// Runs git in `dir` and returns its trimmed stdout, or its stderr if it failed.
+pub fn read(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = run(Some(dir), args)?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(Error::Git(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
@@ -0,0 +1,116 @@
+﻿use crate::cli::plan::Plan;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Component, Path};
+use uuid::Uuid;
+
+/// How the GUIDs of the `.meta` files are made.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuidMode {
+    /// Derived from the package name and the path, the same on every run.
+    #[default]
+    Stable,
+    Random,
+}
+
+pub const GUID_MODES: [&str; 2] = ["stable", "random"];
+
+impl GuidMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stable" => Some(GuidMode::Stable),
+            "random" => Some(GuidMode::Random),
+            _ => None,
+        }
+    }
+}
+
+/// The GUID Unity gets for the asset at `path` inside the package `package`.
+pub fn guid(mode: GuidMode, package: &str, path: &Path) -> String {
+    let uuid = match mode {
+        GuidMode::Stable => {
+            let path = path.to_string_lossy().replace('\\', "/");
+            Uuid::new_v5(&Uuid::NAMESPACE_URL, format!("{}/{}", package, path).as_bytes())
+        },
+        GuidMode::Random => Uuid::new_v4(),
+    };
+    uuid.simple().to_string()
+}
+
+/// Adds a `.meta` file next to every file and folder planned inside the Unity package at
+/// `dir`. Assets Unity does not import, such as `Documentation~`, get none.
+pub fn add_metas(plan: &mut Plan, dir: &Path, package: &str, mode: GuidMode) {
+    for (path, is_dir) in plan.paths_in(dir) {
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        if relative.components().any(is_hidden) || is_meta(&path) {
+            continue;
+        }
+        plan_meta(plan, dir, package, mode, &path, is_dir);
+    }
+}
+
+/// Plans the `.meta` file of the asset at `path` inside the Unity package at `dir` and
+/// returns its GUID, so other files can reference the asset before all metas are added.
+/// A `.meta` file already planned, e.g. by a template pack, or already on disk is kept so
+/// the GUIDs of an existing package do not change.
+pub fn plan_meta(plan: &mut Plan, dir: &Path, package: &str, mode: GuidMode, path: &Path, is_dir: bool) -> String {
+    let meta_path = path.with_file_name(format!("{}.meta", path.file_name().unwrap_or_default().to_string_lossy()));
+    let existing = plan.file(&meta_path)
+        .map(String::from)
+        .or_else(|| fs::read_to_string(plan.root().join(&meta_path)).ok());
+    if let Some(existing) = existing
+        && let Some(guid) = parse_guid(&existing) {
+        plan.add_file(&meta_path, existing);
+        return guid;
+    }
+
+    let guid = guid(mode, package, path.strip_prefix(dir).unwrap_or(path));
+    plan.add_file(&meta_path, meta(&guid, is_dir, path));
+    guid
+}
+
+/// The `guid:` of a `.meta` file.
+pub fn parse_guid(meta: &str) -> Option<String> {
+    meta.lines()
+        .find_map(|line| line.strip_prefix("guid:"))
+        .map(|guid| guid.trim().to_string())
+        .filter(|guid| !guid.is_empty())
+}
+
+fn meta(guid: &str, is_dir: bool, path: &Path) -> String {
+    let extension = path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let importer = match (is_dir, file_name.as_ref(), extension.as_str()) {
+        (true, _, _) => "DefaultImporter",
+        (_, "package.json", _) => "PackageManifestImporter",
+        (_, _, "asmdef") => "AssemblyDefinitionImporter",
+        (_, _, "asmref") => "AssemblyDefinitionReferenceImporter",
+        (_, _, "cs") => "MonoImporter",
+        (_, _, "md" | "txt" | "json" | "xml" | "html" | "yaml" | "yml" | "csv" | "bytes") => "TextScriptImporter",
+        _ => "DefaultImporter",
+    };
+
+    let mut content = format!("fileFormatVersion: 2\nguid: {}\n", guid);
+    if is_dir {
+        content.push_str("folderAsset: yes\n");
+    }
+    content.push_str(&format!("{}:\n  externalObjects: {{}}\n", importer));
+    if importer == "MonoImporter" {
+        content.push_str("  serializedVersion: 2\n  defaultReferences: []\n  executionOrder: 0\n  icon: {instanceID: 0}\n");
+    }
+    content.push_str("  userData: \n  assetBundleName: \n  assetBundleVariant: \n");
+    content
+}
+
+/// Names Unity skips when importing: hidden files and folders ending with `~`.
+fn is_hidden(component: Component) -> bool {
+    let name = component.as_os_str().to_string_lossy();
+    name.starts_with('.') || name.ends_with('~')
+}
+
+fn is_meta(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "meta")
+}
\ No newline at end of file
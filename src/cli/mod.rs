@@ -2,15 +2,31 @@
 mod repositories;
 mod project;
 mod unity_project;
+mod unity_editor;
 mod packages_manifest;
+mod packages_lock;
 mod upm;
-mod dot_net_project;
+mod config;
+mod error;
+mod git;
+mod meta;
+mod output;
+mod plan;
+mod template;
+mod workspace;
+#[cfg(test)]
+mod test_fixtures;
 
-use clap::Command;
+use clap::{Arg, Command};
+use colored::Colorize;
+use std::process;
 
 pub fn run() {
     let command = build();
-    handle(command);
+    if let Err(e) = handle(command) {
+        eprintln!("{} {}\n", "✗".red(), e);
+        process::exit(e.exit_code());
+    }
 }
 
 fn build() -> Command {
@@ -18,16 +34,38 @@ fn build() -> Command {
         .version("1.0.0")
         .author("Maksim Epishev - epishev.m@mail.ru")
         .about("Tool for working with E314")
+        .arg(
+            Arg::new("registry")
+                .long("registry")
+                .value_name("PATH")
+                .help("Path to the module registry file (YAML or JSON)")
+                .global(true))
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(output::FORMATS)
+                .default_value("table")
+                .help("Output format of the inspection commands")
+                .global(true))
         .subcommand(commands::build_list())
         .subcommand(commands::build_clone())
+        .subcommand(commands::build_update())
+        .subcommand(commands::build_status())
         .subcommand(commands::build_new())
+        .subcommand(commands::build_registry())
+        .subcommand(commands::build_unity())
 }
 
-fn handle(command: Command) {
+fn handle(command: Command) -> error::Result<()> {
     match command.get_matches().subcommand() {
-        Some(("list", _)) => commands::execute_list(),
+        Some(("list", args)) => commands::execute_list(args),
         Some(("clone", args)) => commands::execute_clone(args),
-        Some(("new", _)) => commands::execute_new(),
-        _ => {}
+        Some(("update", args)) => commands::execute_update(args),
+        Some(("status", args)) => commands::execute_status(args),
+        Some(("new", args)) => commands::execute_new(args),
+        Some(("registry", args)) => commands::execute_registry(args),
+        Some(("unity", args)) => commands::execute_unity(args),
+        _ => Ok(())
     }
 }
\ No newline at end of file
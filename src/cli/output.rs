@@ -0,0 +1,39 @@
+﻿use crate::cli::error::{Error, Result};
+use clap::ArgMatches;
+use serde::Serialize;
+
+pub const FORMATS: [&str; 3] = ["table", "json", "yaml"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+}
+
+/// Top-level document of the machine-readable output, e.g. `{ "modules": [...] }`.
+#[derive(Debug, Serialize)]
+pub struct Modules<'a, T: Serialize> {
+    pub modules: &'a [T],
+}
+
+impl OutputFormat {
+    pub fn from_args(args: &ArgMatches) -> Self {
+        match args.get_one::<String>("format").map(String::as_str) {
+            Some("json") => OutputFormat::Json,
+            Some("yaml") => OutputFormat::Yaml,
+            _ => OutputFormat::Table,
+        }
+    }
+
+    /// Prints `value` as JSON or YAML. Tables are printed by each command itself.
+    pub fn print<T: Serialize>(&self, value: &T) -> Result<()> {
+        let to_error = |e: String| Error::Io(format!("Output could not be written - {}", e));
+        match self {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value).map_err(|e| to_error(e.to_string()))?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value).map_err(|e| to_error(e.to_string()))?),
+            OutputFormat::Table => {},
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,543 @@
+﻿use crate::cli::config::user_cache_dir;
+use crate::cli::error::{Error, Result};
+use crate::cli::git;
+use crate::cli::packages_manifest::{PackagesManifest, ScopedRegistry};
+use crate::cli::plan::{to_json, Plan};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// Registry of the packages outside every scoped registry.
+const UNITY_REGISTRY: &str = "https://packages.unity.com";
+/// Packages of the Unity modules, shipped with the editor.
+const BUILTIN_PREFIX: &str = "com.unity.modules.";
+
+/// The `Packages/packages-lock.json` of a Unity project. Fields it doesn't know are kept
+/// when a lock file is read and written back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackagesLock {
+    pub dependencies: IndexMap<String, PackageInfo>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+    #[serde(skip)]
+    trailing_newline: bool,
+}
+
+/// A resolved package, in the shape Unity writes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInfo {
+    pub version: String,
+    /// 0 for the packages of the manifest, 1 for their dependencies and so on.
+    pub depth: u32,
+    /// `registry`, `git`, `embedded`, `local` or `builtin`.
+    pub source: String,
+    #[serde(default)]
+    pub dependencies: IndexMap<String, String>,
+    /// Registry the package comes from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Commit a git package is locked to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl PackagesLock {
+    /// The lock file at `path`, if there is one.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Io(format!("{} - {}", path.display(), e))),
+        };
+        let mut lock: PackagesLock = serde_json::from_str(&content)
+            .map_err(|e| Error::InvalidInput(format!("{} is malformed: {}", path.display(), e)))?;
+        lock.trailing_newline = content.ends_with('\n');
+        Ok(Some(lock))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?).map_err(|e| Error::Io(format!("{} - {}", path.display(), e)))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let json = to_json(self)?;
+        Ok(match self.trailing_newline {
+            true => json,
+            false => json.trim_end().to_string(),
+        })
+    }
+}
+
+/// Resolves the dependencies of a manifest the way the Package Manager does: the manifest
+/// decides the version of its own packages, and of the versions other packages ask for
+/// the highest one wins. The dependencies of a registry package are read from the
+/// registry documents cached in `~/.cache/e314/registry`, fetched when missing, and those
+/// of a git package from a shallow clone of the locked tag. Registry and git packages
+/// already in `previous` at the same version are taken from it as they are.
+pub struct Resolver<'a> {
+    scoped_registries: Vec<ScopedRegistry>,
+    previous: Option<&'a PackagesLock>,
+    /// Use only the cached registry documents: nothing is fetched, cloned or written.
+    offline: bool,
+    documents: HashMap<String, Option<Value>>,
+    clones: HashMap<String, Option<(String, Value)>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(scoped_registries: Vec<ScopedRegistry>, previous: Option<&'a PackagesLock>, offline: bool) -> Self {
+        Resolver { scoped_registries, previous, offline, documents: HashMap::new(), clones: HashMap::new() }
+    }
+
+    /// Resolves the manifest of the `Packages` folder `dir`, relative to the root of `plan`
+    /// like the planned files. Fails when the dependencies of a package can't be read, since
+    /// a lock file without them would be rewritten by Unity.
+    pub fn resolve(&mut self, manifest: &PackagesManifest, plan: &Plan, dir: &Path) -> Result<PackagesLock> {
+        let mut roots: BTreeMap<String, String> = manifest.dependencies().into_iter().collect();
+        for (name, folder) in embedded_packages(plan, dir) {
+            roots.entry(name).or_insert(format!("file:{}", folder));
+        }
+
+        // The versions only grow from one pass to the next, so this ends.
+        let mut chosen = roots.clone();
+        loop {
+            let mut locked = BTreeMap::new();
+            let mut next = roots.clone();
+            let mut queue: VecDeque<(String, String, u32)> = roots.iter()
+                .map(|(name, version)| (name.clone(), version.clone(), 0))
+                .collect();
+            while let Some((name, version, depth)) = queue.pop_front() {
+                if locked.contains_key(&name) {
+                    continue;
+                }
+                let version = chosen.get(&name).cloned().unwrap_or(version);
+                let package = self.package(&name, &version, depth, plan, dir)?;
+                for (dep, dep_version) in &package.dependencies {
+                    if !roots.contains_key(dep) {
+                        let wanted = next.entry(dep.clone()).or_insert(dep_version.clone());
+                        if compare_versions(dep_version, wanted) == Ordering::Greater {
+                            *wanted = dep_version.clone();
+                        }
+                    }
+                    queue.push_back((dep.clone(), dep_version.clone(), depth + 1));
+                }
+                locked.insert(name, package);
+            }
+            for (name, version) in &chosen {
+                if let Some(wanted) = next.get_mut(name) && compare_versions(version, wanted) == Ordering::Greater {
+                    *wanted = version.clone();
+                }
+            }
+            if next == chosen {
+                return Ok(PackagesLock {
+                    dependencies: self.ordered(locked),
+                    other: self.previous.map(|lock| lock.other.clone()).unwrap_or_default(),
+                    trailing_newline: self.previous.is_none_or(|lock| lock.trailing_newline),
+                });
+            }
+            chosen = next;
+        }
+    }
+
+    /// Packages in alphabetical order, as the Package Manager writes them, unless the
+    /// previous lock file has another order: then its packages keep their places.
+    fn ordered(&self, mut locked: BTreeMap<String, PackageInfo>) -> IndexMap<String, PackageInfo> {
+        let mut ordered = IndexMap::new();
+        if let Some(previous) = self.previous && !previous.dependencies.keys().is_sorted() {
+            for name in previous.dependencies.keys() {
+                if let Some(package) = locked.remove(name) {
+                    ordered.insert(name.clone(), package);
+                }
+            }
+        }
+        ordered.extend(locked);
+        ordered
+    }
+
+    fn package(&mut self, name: &str, version: &str, depth: u32, plan: &Plan, dir: &Path) -> Result<PackageInfo> {
+        let previous = self.previous.and_then(|lock| lock.dependencies.get(name))
+            .filter(|package| package.version == version && ["registry", "git"].contains(&package.source.as_str()));
+        if let Some(previous) = previous {
+            return Ok(PackageInfo { depth, ..previous.clone() });
+        }
+
+        let mut package = PackageInfo {
+            version: version.to_string(),
+            depth,
+            source: String::new(),
+            dependencies: IndexMap::new(),
+            url: None,
+            hash: None,
+            other: Map::new(),
+        };
+        package.dependencies = if is_git_url(version) {
+            package.source = "git".to_string();
+            let (hash, package_json) = self.clone_package(version)?;
+            package.hash = Some(hash);
+            dependencies_of(&package_json)
+        } else if let Some(path) = version.strip_prefix("file:") {
+            let folder = normalize(&dir.join(path));
+            package.source = match folder.parent() == Some(dir) {
+                true => "embedded",
+                false => "local",
+            }.to_string();
+            dependencies_of(&read_package(plan, &folder.join("package.json"))?)
+        } else if name.starts_with(BUILTIN_PREFIX) {
+            package.source = "builtin".to_string();
+            IndexMap::new()
+        } else {
+            let url = self.registry_url(name);
+            package.source = "registry".to_string();
+            package.url = Some(url.clone());
+            dependencies_of(&self.registry_document(&url, name, version)?["versions"][version])
+        };
+        Ok(package)
+    }
+
+    /// The scoped registry with the longest scope that matches `name`.
+    fn registry_url(&self, name: &str) -> String {
+        self.scoped_registries.iter()
+            .flat_map(|registry| registry.scopes.iter().map(move |scope| (scope, registry)))
+            .filter(|(scope, _)| name == scope.as_str() || name.starts_with(&format!("{}.", scope)))
+            .max_by_key(|(scope, _)| scope.len())
+            .map(|(_, registry)| registry.url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| UNITY_REGISTRY.to_string())
+    }
+
+    /// The registry document of `name`, from the cache when it has `version`.
+    fn registry_document(&mut self, url: &str, name: &str, version: &str) -> Result<Value> {
+        let cache_path = user_cache_dir().map(|dir| dir.join("registry").join(host(url)).join(format!("{}.json", name)));
+        let key = format!("{}/{}", url, name);
+        if !self.documents.contains_key(&key) {
+            let cached = cache_path.as_ref()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+            self.documents.insert(key.clone(), cached);
+        }
+        if let Some(Some(document)) = self.documents.get(&key) && document["versions"].get(version).is_some() {
+            return Ok(document.clone());
+        }
+
+        if self.offline {
+            return Err(Error::Io(format!("{} with version {} is not in the cache", key, version)));
+        }
+        let content = fetch(&key)?;
+        let document: Value = serde_json::from_slice(&content)
+            .map_err(|e| Error::Io(format!("{} is malformed: {}", key, e)))?;
+        if document["versions"].get(version).is_none() {
+            return Err(Error::Io(format!("{} has no version {}", key, version)));
+        }
+        if let Some(path) = cache_path {
+            let _ = fs::create_dir_all(path.parent().unwrap_or(Path::new("")));
+            let _ = fs::write(&path, &content);
+        }
+        self.documents.insert(key, Some(document.clone()));
+        Ok(document)
+    }
+
+    /// Commit and `package.json` of a git package URL such as `<repo>?path=UPM#1.0.0`.
+    fn clone_package(&mut self, url: &str) -> Result<(String, Value)> {
+        if let Some(cloned) = self.clones.get(url) {
+            return cloned.clone().ok_or_else(|| Error::Git(format!("{} could not be cloned", url)));
+        }
+        if self.offline {
+            return Err(Error::Git(format!("{} is not cloned without network access", url)));
+        }
+        let result = clone_package(url);
+        self.clones.insert(url.to_string(), result.as_ref().ok().cloned());
+        result
+    }
+}
+
+/// The body of a successful GET of `url`.
+fn fetch(url: &str) -> Result<Vec<u8>> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_connect(Some(Duration::from_secs(10)))
+        .timeout_global(Some(Duration::from_secs(60)))
+        .build()
+        .into();
+    let to_error = |e: ureq::Error| Error::Io(format!("{} could not be fetched: {}", url, e));
+    agent.get(url).call().map_err(to_error)?
+        .body_mut()
+        .with_config()
+        .limit(64 * 1024 * 1024)
+        .read_to_vec()
+        .map_err(to_error)
+}
+
+fn clone_package(url: &str) -> Result<(String, Value)> {
+    let (repo, revision) = url.split_once('#').map_or((url, None), |(repo, revision)| (repo, Some(revision)));
+    let (repo, path) = repo.split_once("?path=").unwrap_or((repo, ""));
+    let repo = repo.strip_prefix("git+").unwrap_or(repo);
+
+    let tmp_path = env::temp_dir().join(format!("e314-lock-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp_path);
+    let tmp_dir = tmp_path.to_string_lossy();
+    let mut args = vec!["clone", "--quiet", "--depth", "1"];
+    if let Some(revision) = revision {
+        args.extend(["--branch", revision]);
+    }
+    args.extend([repo, &tmp_dir]);
+    let output = git::run(None, &args)?;
+
+    let result = if output.status.success() {
+        git::read(&tmp_path, &["rev-parse", "HEAD"]).and_then(|hash| {
+            let package_path = tmp_path.join(path.trim_matches('/')).join("package.json");
+            let content = fs::read_to_string(&package_path)
+                .map_err(|e| Error::Git(format!("{} - package.json: {}", url, e)))?;
+            let package = serde_json::from_str(&content)
+                .map_err(|e| Error::Git(format!("{} - package.json is malformed: {}", url, e)))?;
+            Ok((hash, package))
+        })
+    } else {
+        Err(Error::Git(format!("{} - {}", url, String::from_utf8_lossy(&output.stderr).trim())))
+    };
+
+    let _ = fs::remove_dir_all(&tmp_path);
+    result
+}
+
+/// Folders of `dir` holding a package, planned or on disk under the root of `plan`, mapped
+/// by package name.
+fn embedded_packages(plan: &Plan, dir: &Path) -> BTreeMap<String, String> {
+    let mut folders: Vec<PathBuf> = plan.paths_in(dir).into_iter()
+        .filter(|(path, is_dir)| *is_dir && path.parent() == Some(dir))
+        .map(|(path, _)| path)
+        .collect();
+    if let Ok(entries) = fs::read_dir(plan.root().join(dir)) {
+        folders.extend(entries.flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| dir.join(entry.file_name())));
+    }
+
+    let mut packages = BTreeMap::new();
+    for folder in folders {
+        let name = read_package(plan, &folder.join("package.json"))
+            .ok()
+            .and_then(|package| package["name"].as_str().map(String::from));
+        if let (Some(name), Some(folder_name)) = (name, folder.file_name()) {
+            packages.insert(name, folder_name.to_string_lossy().to_string());
+        }
+    }
+    packages
+}
+
+/// A `package.json` at `path`, planned or on disk under the root of `plan`.
+fn read_package(plan: &Plan, path: &Path) -> Result<Value> {
+    let content = match plan.file(path) {
+        Some(content) => content.to_string(),
+        None => fs::read_to_string(plan.root().join(path))
+            .map_err(|e| Error::Io(format!("{} - {}", path.display(), e)))?,
+    };
+    serde_json::from_str(&content).map_err(|e| Error::Io(format!("{} is malformed: {}", path.display(), e)))
+}
+
+fn dependencies_of(package: &Value) -> IndexMap<String, String> {
+    package["dependencies"].as_object()
+        .map(|dependencies| dependencies.iter()
+            .filter_map(|(name, version)| version.as_str().map(|version| (name.clone(), version.to_string())))
+            .collect())
+        .unwrap_or_default()
+}
+
+fn is_git_url(version: &str) -> bool {
+    let url = version.split(['?', '#']).next().unwrap_or(version);
+    version.starts_with("git+") || version.starts_with("git@") || version.starts_with("ssh://")
+        || (url.contains("://") && url.ends_with(".git"))
+}
+
+fn host(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    url.replace(['/', ':'], "_")
+}
+
+/// Removes the `.` and `..` components of `path` without touching the disk.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Orders `major.minor.patch[-pre]` versions; a pre-release comes before its release.
+/// Anything else is ordered as text.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |version: &str| -> Option<(Vec<u64>, Option<String>)> {
+        let (release, pre) = match version.split_once('-') {
+            Some((release, pre)) => (release, Some(pre.to_string())),
+            None => (version, None),
+        };
+        let numbers = release.split('.').map(|part| part.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        Some((numbers, pre))
+    };
+    match (parse(a), parse(b)) {
+        (Some((a_numbers, a_pre)), Some((b_numbers, b_pre))) => a_numbers.cmp(&b_numbers).then(match (a_pre, b_pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a_pre), Some(b_pre)) => a_pre.cmp(&b_pre),
+        }),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::test_fixtures::TempDir;
+
+    const LOCK: &str = r#"{
+  "dependencies": {
+    "com.unity.test-framework": {
+      "version": "1.1.24",
+      "depth": 0,
+      "source": "registry",
+      "dependencies": {
+        "com.unity.ext.nunit": "1.0.6"
+      },
+      "url": "https://packages.unity.com"
+    },
+    "com.unity.ext.nunit": {
+      "version": "1.0.6",
+      "depth": 1,
+      "source": "registry",
+      "dependencies": {},
+      "url": "https://packages.unity.com",
+      "signature": "unknown"
+    }
+  },
+  "lockVersion": 2
+}"#;
+
+    #[test]
+    fn keeps_unknown_fields_order_and_missing_trailing_newline() {
+        let dir = TempDir::new("lock-keep");
+        let path = dir.write("packages-lock.json", LOCK);
+        let lock = PackagesLock::load(&path).unwrap().unwrap();
+        lock.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), LOCK);
+    }
+
+    const REGISTRY: &str = "https://registry.test";
+
+    /// A package version of the test registry and the dependencies it asks for.
+    type TestPackage<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+    /// A cache-only resolver for the `com.test` packages, whose registry documents give
+    /// each `(name, version)` the dependencies listed with it.
+    fn resolver(packages: &[TestPackage]) -> Resolver<'static> {
+        let registry = ScopedRegistry {
+            name: String::from("Test"),
+            url: String::from(REGISTRY),
+            scopes: vec![String::from("com.test")],
+        };
+        let mut resolver = Resolver::new(vec![registry], None, true);
+        for (name, version, dependencies) in packages {
+            let document = resolver.documents.entry(format!("{}/{}", REGISTRY, name))
+                .or_insert_with(|| Some(serde_json::json!({ "versions": {} })))
+                .as_mut()
+                .unwrap();
+            let dependencies: Map<String, Value> = dependencies.iter()
+                .map(|(dep, dep_version)| (dep.to_string(), Value::String(dep_version.to_string())))
+                .collect();
+            document["versions"][*version] = serde_json::json!({ "dependencies": dependencies });
+        }
+        resolver
+    }
+
+    fn resolve(resolver: &mut Resolver, case: &str, dependencies: &str) -> Result<PackagesLock> {
+        let dir = TempDir::new(&format!("resolve-{}", case));
+        let path = dir.write("Packages/manifest.json", format!(r#"{{ "dependencies": {{ {} }} }}"#, dependencies));
+        let manifest = PackagesManifest::load(&path).unwrap();
+        resolver.resolve(&manifest, &Plan::new(dir.path()), Path::new("Packages"))
+    }
+
+    fn versions(lock: &PackagesLock) -> Vec<(&str, &str, u32)> {
+        lock.dependencies.iter()
+            .map(|(name, package)| (name.as_str(), package.version.as_str(), package.depth))
+            .collect()
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("2.0.0-pre.1", "2.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0.0-pre.2", "2.0.0-pre.1"), Ordering::Greater);
+        assert_eq!(compare_versions("2.0.0-pre.1", "1.9.9"), Ordering::Greater);
+        assert_eq!(compare_versions("file:../b", "file:../a"), Ordering::Greater);
+    }
+
+    #[test]
+    fn the_highest_requested_version_wins_and_drops_what_only_the_lower_one_needs() {
+        let mut resolver = resolver(&[
+            ("com.test.a", "1.0.0", &[("com.test.c", "1.0.0")]),
+            ("com.test.b", "1.0.0", &[("com.test.c", "2.0.0")]),
+            ("com.test.c", "1.0.0", &[("com.test.d", "1.0.0")]),
+            ("com.test.c", "2.0.0", &[("com.test.e", "1.0.0")]),
+            ("com.test.d", "1.0.0", &[]),
+            ("com.test.e", "1.0.0", &[]),
+        ]);
+        let lock = resolve(&mut resolver, "highest", r#""com.test.a": "1.0.0", "com.test.b": "1.0.0""#).unwrap();
+        assert_eq!(versions(&lock), [
+            ("com.test.a", "1.0.0", 0),
+            ("com.test.b", "1.0.0", 0),
+            ("com.test.c", "2.0.0", 1),
+            ("com.test.e", "1.0.0", 2),
+        ]);
+        assert_eq!(lock.dependencies["com.test.c"].url.as_deref(), Some(REGISTRY));
+        assert_eq!(lock.dependencies["com.test.c"].source, "registry");
+    }
+
+    #[test]
+    fn the_manifest_decides_the_version_of_its_packages() {
+        let mut resolver = resolver(&[
+            ("com.test.a", "1.0.0", &[("com.test.c", "2.0.0")]),
+            ("com.test.c", "1.0.0", &[]),
+        ]);
+        let lock = resolve(&mut resolver, "manifest", r#""com.test.a": "1.0.0", "com.test.c": "1.0.0""#).unwrap();
+        assert_eq!(versions(&lock), [("com.test.a", "1.0.0", 0), ("com.test.c", "1.0.0", 0)]);
+    }
+
+    #[test]
+    fn fails_without_the_registry_document_of_a_package() {
+        let mut resolver = resolver(&[("com.test.a", "1.0.0", &[("com.test.missing", "1.0.0")])]);
+        let lock = resolve(&mut resolver, "missing", r#""com.test.a": "1.0.0""#);
+        assert!(matches!(lock, Err(Error::Io(message)) if message.contains("com.test.missing")));
+    }
+
+    #[test]
+    fn reads_the_embedded_packages_under_the_plan_root_and_not_the_working_directory() {
+        let mut resolver = resolver(&[("com.test.a", "1.0.0", &[])]);
+        let root = TempDir::new("resolve-root");
+        let path = root.write("Packages/manifest.json", r#"{ "dependencies": {} }"#);
+        root.write("Packages/Tools/package.json", r#"{ "name": "com.test.tools", "dependencies": { "com.test.a": "1.0.0" } }"#);
+        let manifest = PackagesManifest::load(&path).unwrap();
+        assert_ne!(env::current_dir().unwrap(), root.path());
+
+        let lock = resolver.resolve(&manifest, &Plan::new(root.path()), Path::new("Packages")).unwrap();
+
+        assert_eq!(versions(&lock), [("com.test.a", "1.0.0", 1), ("com.test.tools", "file:Tools", 0)]);
+        assert_eq!(lock.dependencies["com.test.tools"].source, "embedded");
+    }
+
+    #[test]
+    fn a_missing_lock_file_is_none() {
+        let dir = TempDir::new("lock-missing");
+        assert!(PackagesLock::load(&dir.path().join("packages-lock.json")).unwrap().is_none());
+    }
+}
\ No newline at end of file
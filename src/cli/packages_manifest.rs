@@ -1,81 +1,292 @@
-﻿use std::collections::HashMap;
+﻿use crate::cli::config::is_valid_scope;
+use crate::cli::error::{Error, Result};
+use crate::cli::packages_lock::Resolver;
+use crate::cli::plan::{to_json, Plan};
+use crate::cli::repositories::RepoInfo;
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
-use serde_json;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PackageInfo {
-    pub version: String,
-    pub depth: u32,
-    pub source: String,
-    pub dependencies: Option<HashMap<String, String>>,
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Where the Unity project takes the E314 dependencies of the module from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencySource {
+    /// The git repository of the module at the tag of the registered version.
+    #[default]
+    Git,
+    /// A package registry, by version.
+    Registry,
+}
+
+pub const DEPENDENCY_SOURCES: [&str; 2] = ["git", "registry"];
+
+impl DependencySource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "git" => Some(DependencySource::Git),
+            "registry" => Some(DependencySource::Registry),
+            _ => None,
+        }
+    }
+}
+
+/// A package registry the Unity project resolves the packages of some scopes from,
+/// e.g. OpenUPM for `com.e314`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScopedRegistry {
+    pub name: String,
+    pub url: String,
+    /// Package name prefixes taken from the registry.
+    pub scopes: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ScopedRegistry {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Config(format!("Scoped registry '{}' has no name", self.url)));
+        }
+        if !["http://", "https://"].iter().any(|scheme| self.url.starts_with(scheme)) {
+            return Err(Error::Config(format!("Scoped registry '{}' - '{}' is not an http(s) URL",
+                                             self.name, self.url)));
+        }
+        if self.scopes.is_empty() {
+            return Err(Error::Config(format!("Scoped registry '{}' has no scopes", self.name)));
+        }
+        match self.scopes.iter().find(|scope| !is_valid_scope(scope)) {
+            Some(scope) => Err(Error::Config(format!("Scoped registry '{}' - scope '{}' is not a lowercase reverse domain",
+                                                     self.name, scope))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The `Packages/manifest.json` of a Unity project. The document is kept as it was read,
+/// so editing a manifest leaves the fields it doesn't know and the order of the keys alone.
+#[derive(Debug)]
 pub struct PackagesManifest {
-    #[serde(flatten)]
-    pub packages: HashMap<String, PackageInfo>,
+    document: Map<String, Value>,
+    trailing_newline: bool,
 }
 
 impl PackagesManifest {
     pub fn new() -> Self {
-        Self {
-            packages: HashMap::from([
-                ("com.unity.test-framework".to_string(),
-                 PackageInfo {
-                     version: "1.1.24".to_string(),
-                     depth: 0,
-                     source: "https://registry.npmjs.org".to_string(),
-                     dependencies: Some(HashMap::from([
-                         ("com.unity.ext.nunit".to_string(), "2.0.3".to_string()),
-                         ("com.unity.modules.imgui".to_string(), "1.0.0".to_string()),
-                         ("com.unity.modules.jsonserialize".to_string(), "1.0.0".to_string())
-                     ])),
-                 }),
-                ("com.unity.ide.rider".to_string(),
-                 PackageInfo {
-                     version: "3.0.36".to_string(),
-                     depth: 0,
-                     source: "registry".to_string(),
-                     dependencies: Some(HashMap::from([
-                         ("com.unity.ext.nunit".to_string(), "1.0.6".to_string()),
-                     ])),
-                 }),
-                ("com.unity.testtools.codecoverage".to_string(),
-                 PackageInfo {
-                     version: "1.2.6".to_string(),
-                     depth: 0,
-                     source: "registry".to_string(),
-                     dependencies: Some(HashMap::from([
-                         ("com.unity.test-framework".to_string(), "1.0.16".to_string()),
-                         ("com.unity.settings-manager".to_string(), "1.0.1".to_string()),
-                     ])),
-                 }),
-            ])
+        let mut manifest = PackagesManifest { document: Map::new(), trailing_newline: true };
+        manifest.set_dependency("com.unity.ide.rider", "3.0.36");
+        manifest.set_dependency("com.unity.test-framework", "1.1.24");
+        manifest.set_dependency("com.unity.testtools.codecoverage", "1.2.6");
+        manifest
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::Io(format!("{} - {}", path.display(), e)))?;
+        let document = serde_json::from_str(&content)
+            .map_err(|e| Error::InvalidInput(format!("{} is malformed: {}", path.display(), e)))?;
+        Ok(PackagesManifest { document, trailing_newline: content.ends_with('\n') })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?).map_err(|e| Error::Io(format!("{} - {}", path.display(), e)))
+    }
+
+    /// Direct dependencies of the project, mapped to a version, a git URL or a `file:` path.
+    pub fn dependencies(&self) -> Vec<(String, String)> {
+        self.document.get("dependencies")
+            .and_then(Value::as_object)
+            .map(|dependencies| dependencies.iter()
+                .filter_map(|(name, version)| version.as_str().map(|version| (name.clone(), version.to_string())))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Adds a dependency or changes its version. New names are kept in alphabetical order
+    /// when the dependencies already are, as the Package Manager writes them.
+    pub fn set_dependency(&mut self, name: &str, version: &str) {
+        let dependencies = object(&mut self.document, "dependencies");
+        let sorted = dependencies.keys().is_sorted();
+        dependencies.insert(name.to_string(), Value::String(version.to_string()));
+        if sorted {
+            dependencies.sort_keys();
+        }
+    }
+
+    /// Removes a dependency and its testable, returning its version. `testables` is
+    /// removed when its last package is.
+    pub fn remove_dependency(&mut self, name: &str) -> Option<String> {
+        let removed = self.document.get_mut("dependencies")
+            .and_then(Value::as_object_mut)
+            .and_then(|dependencies| dependencies.shift_remove(name))?;
+        if let Some(testables) = self.document.get_mut("testables").and_then(Value::as_array_mut)
+            && let Some(index) = testables.iter().position(|testable| testable.as_str() == Some(name)) {
+            testables.remove(index);
+            if testables.is_empty() {
+                self.document.shift_remove("testables");
+            }
+        }
+        Some(removed.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Registries of the manifest that have a URL and scopes.
+    pub fn scoped_registries(&self) -> Vec<ScopedRegistry> {
+        self.document.get("scopedRegistries")
+            .and_then(Value::as_array)
+            .map(|registries| registries.iter()
+                .filter_map(|registry| Some(ScopedRegistry {
+                    name: registry["name"].as_str().unwrap_or_default().to_string(),
+                    url: registry["url"].as_str()?.to_string(),
+                    scopes: registry["scopes"].as_array()?.iter()
+                        .filter_map(|scope| scope.as_str().map(String::from))
+                        .collect(),
+                }))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    pub fn add_scoped_registry(&mut self, registry: ScopedRegistry) {
+        let registries = array(&mut self.document, "scopedRegistries");
+        if !registries.iter().any(|existing| existing["url"].as_str() == Some(&registry.url)) {
+            registries.push(serde_json::json!(registry));
         }
     }
 
-    pub fn create_packages_lock(&self, dir: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let path = Path::new(dir).join("packages-lock.json");
-        let json = serde_json::to_string_pretty(self)?;
-        std::fs::write(path, json)?;
+    pub fn add_testable(&mut self, name: &str) {
+        let testables = array(&mut self.document, "testables");
+        if !testables.iter().any(|testable| testable.as_str() == Some(name)) {
+            testables.push(Value::String(name.to_string()));
+        }
+    }
+
+    /// Whether the Package Manager keeps `packages-lock.json`, `enableLockFile`.
+    pub fn lock_file_enabled(&self) -> bool {
+        self.document.get("enableLockFile").and_then(Value::as_bool).unwrap_or(true)
+    }
+
+    /// Adds the package in development from `path`, relative to the `Packages` folder,
+    /// and makes its tests runnable.
+    pub fn add_local(&mut self, name: &str, path: &str) {
+        self.set_dependency(name, &format!("file:{}", path));
+        self.add_testable(name);
+    }
+
+    /// Adds an E314 module the package depends on and makes its tests runnable.
+    pub fn add_dependency(&mut self, dep: &RepoInfo, source: DependencySource) -> Result<()> {
+        let version = match source {
+            DependencySource::Git => dep.package_url()?,
+            DependencySource::Registry => dep.version.clone(),
+        };
+        self.set_dependency(&dep.name, &version);
+        self.add_testable(&dep.name);
         Ok(())
     }
-    
-    pub fn create_manifest(&self, dir: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let path = Path::new(dir).join("manifest.json");
-        let mut dependencies = HashMap::new();
 
-        for (name, info) in &self.packages {
-            dependencies.insert(name.clone(), info.version.clone());
+    /// Resolves the packages of the manifest in `dir` into its lock file. When that fails
+    /// no lock file is planned, and Unity creates it when the project is opened.
+    pub fn create_packages_lock(&self, plan: &mut Plan, dir: &Path, offline: bool) -> Result<()> {
+        match Resolver::new(self.scoped_registries(), None, offline).resolve(self, plan, dir) {
+            Ok(lock) => plan.add_file(dir.join("packages-lock.json"), lock.to_json()?),
+            Err(e) => println!("  {} packages-lock.json - {}", "!".yellow(),
+                               format!("not generated, Unity creates it: {}", e.message()).yellow()),
         }
+        Ok(())
+    }
 
-        let manifest = serde_json::json!({
-            "dependencies": dependencies
+    pub fn create_manifest(&self, plan: &mut Plan, dir: &Path) -> Result<()> {
+        plan.add_file(dir.join("manifest.json"), self.to_json()?);
+        Ok(())
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let json = to_json(&self.document)?;
+        Ok(match self.trailing_newline {
+            true => json,
+            false => json.trim_end().to_string(),
+        })
+    }
+}
+
+/// The object at `key`, made empty first if it is missing or not an object.
+fn object<'a>(document: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let value = document.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value.as_object_mut().unwrap()
+}
+
+/// The array at `key`, made empty first if it is missing or not an array.
+fn array<'a>(document: &'a mut Map<String, Value>, key: &str) -> &'a mut Vec<Value> {
+    let value = document.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+    if !value.is_array() {
+        *value = Value::Array(Vec::new());
+    }
+    value.as_array_mut().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::test_fixtures::TempDir;
+
+    fn round_trip(case: &str, content: &str, edit: impl Fn(&mut PackagesManifest)) -> String {
+        let dir = TempDir::new(&format!("manifest-{}", case));
+        let path = dir.write("manifest.json", content);
+        let mut manifest = PackagesManifest::load(&path).unwrap();
+        edit(&mut manifest);
+        manifest.save(&path).unwrap();
+        fs::read_to_string(&path).unwrap()
+    }
+
+    const MANIFEST: &str = r#"{
+  "dependencies": {
+    "com.unity.ugui": "2.0.0",
+    "com.company.tools": "file:../../Tools"
+  },
+  "registry": "https://packages.unity.com",
+  "enableLockFile": true
+}"#;
+
+    #[test]
+    fn keeps_unknown_fields_order_and_missing_trailing_newline() {
+        assert_eq!(round_trip("keep", MANIFEST, |_| {}), MANIFEST);
+    }
+
+    #[test]
+    fn adding_and_removing_a_dependency_restores_the_manifest() {
+        let dep = RepoInfo {
+            name: String::from("com.e314.protect"),
+            version: String::from("2.1.1"),
+            url: String::from("https://github.com/epishev-m/e314-protect.git"),
+            tag_pattern: None,
+            assemblies: Default::default(),
+        };
+        let saved = round_trip("add-remove", MANIFEST, |manifest| {
+            manifest.add_dependency(&dep, DependencySource::Registry).unwrap();
+            assert_eq!(manifest.remove_dependency("com.e314.protect").as_deref(), Some("2.1.1"));
         });
+        assert_eq!(saved, MANIFEST);
+    }
 
-        let json = serde_json::to_string_pretty(&manifest)?;
-        std::fs::write(path, json)?;
-        Ok(())
+    #[test]
+    fn keeps_other_testables() {
+        let mut manifest = PackagesManifest::new();
+        manifest.add_local("com.e314.foo", "../../UPM");
+        manifest.add_testable("com.unity.test-framework");
+        manifest.remove_dependency("com.e314.foo");
+        assert_eq!(manifest.document["testables"], serde_json::json!(["com.unity.test-framework"]));
+    }
+
+    #[test]
+    fn new_dependencies_keep_the_order_of_the_manifest() {
+        let mut sorted = PackagesManifest::new();
+        sorted.set_dependency("com.e314.foo", "1.0.0");
+        let names: Vec<String> = sorted.dependencies().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["com.e314.foo", "com.unity.ide.rider", "com.unity.test-framework",
+            "com.unity.testtools.codecoverage"]);
+
+        let saved = round_trip("unsorted", MANIFEST, |manifest| manifest.set_dependency("com.e314.foo", "1.0.0"));
+        assert!(saved.contains("\"com.company.tools\": \"file:../../Tools\",\n    \"com.e314.foo\": \"1.0.0\""));
     }
 }
\ No newline at end of file
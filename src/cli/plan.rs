@@ -0,0 +1,466 @@
+﻿use crate::cli::error::{Error, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(Debug)]
+pub enum Entry {
+    Dir(PathBuf),
+    File(PathBuf, String),
+}
+
+/// Directories and files a generator is going to create under a root directory, so they
+/// can be previewed before anything is written.
+#[derive(Debug)]
+pub struct Plan {
+    root: PathBuf,
+    entries: Vec<Entry>,
+}
+
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    file: Option<usize>,
+}
+
+impl Plan {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Plan { root: root.into(), entries: Vec::new() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Content of the file planned at `path`.
+    pub fn file(&self, path: &Path) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            Entry::File(file_path, content) if file_path == path => Some(content.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Planned files and directories inside `dir`, including the directories implied by
+    /// the files, mapped to whether they are directories.
+    pub fn paths_in(&self, dir: &Path) -> BTreeMap<PathBuf, bool> {
+        let mut paths = BTreeMap::new();
+        for entry in &self.entries {
+            let (path, is_dir) = match entry {
+                Entry::Dir(path) => (path, true),
+                Entry::File(path, _) => (path, false),
+            };
+            if !path.starts_with(dir) || path == dir {
+                continue;
+            }
+            paths.insert(path.clone(), is_dir);
+            for ancestor in path.ancestors().skip(1).take_while(|ancestor| *ancestor != dir) {
+                paths.insert(ancestor.to_path_buf(), true);
+            }
+        }
+        paths
+    }
+
+    pub fn add_dir(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        if path.as_os_str().is_empty() {
+            return;
+        }
+        let exists = self.entries.iter().any(|entry| matches!(entry, Entry::Dir(dir) if *dir == path));
+        if !exists {
+            self.entries.push(Entry::Dir(path));
+        }
+    }
+
+    /// Adds a file, replacing the content of a file already planned at the same path.
+    pub fn add_file(&mut self, path: impl AsRef<Path>, content: impl Into<String>) {
+        let path = path.as_ref().to_path_buf();
+        let content = content.into();
+        for entry in self.entries.iter_mut() {
+            if let Entry::File(file_path, file_content) = entry && *file_path == path {
+                *file_content = content;
+                return;
+            }
+        }
+        self.entries.push(Entry::File(path, content));
+    }
+
+    /// Writes the plan to the root directory. The planned files are first written to a
+    /// staging directory next to the root and moved into place only when all of them were
+    /// written, so a failure leaves the root as it was. An existing root is an error unless
+    /// `force` is set; then the files the plan replaces are moved aside to a backup
+    /// directory until every planned file is in place, and moved back on a failure.
+    pub fn apply(&self, force: bool) -> Result<()> {
+        let exists = self.root.exists();
+        if exists && !force {
+            return Err(Error::InvalidInput(format!(
+                "'{}' already exists, use --force to overwrite it", self.root.display())));
+        }
+
+        let staging = self.sibling("new");
+        remove_dir(&staging)?;
+        if let Err(e) = self.write_to(&staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        if !exists {
+            return fs::rename(&staging, &self.root).map_err(|e| {
+                let _ = fs::remove_dir_all(&staging);
+                Error::Io(format!("{} - {}", self.root.display(), e))
+            });
+        }
+
+        let backup = self.sibling("old");
+        remove_dir(&backup)?;
+        let mut journal = Journal::default();
+        let moved = self.move_into_root(&staging, &backup, &mut journal);
+        if moved.is_err() {
+            journal.undo();
+        }
+        let _ = fs::remove_dir_all(&staging);
+        let _ = fs::remove_dir_all(&backup);
+        moved
+    }
+
+    /// Moves the staged entries into the existing root, each file it replaces to `backup`
+    /// first, recording every step in `journal`.
+    fn move_into_root(&self, staging: &Path, backup: &Path, journal: &mut Journal) -> Result<()> {
+        for entry in &self.entries {
+            match entry {
+                Entry::Dir(path) => journal.create_dir_all(&self.root.join(path))?,
+                Entry::File(path, _) => {
+                    let target = self.root.join(path);
+                    if let Some(parent) = target.parent() {
+                        journal.create_dir_all(parent)?;
+                    }
+                    if target.symlink_metadata().is_ok_and(|metadata| !metadata.is_dir()) {
+                        let saved = backup.join(path);
+                        if let Some(parent) = saved.parent() {
+                            fs::create_dir_all(parent)
+                                .map_err(|e| Error::Io(format!("{} - {}", parent.display(), e)))?;
+                        }
+                        journal.rename(&target, &saved)?;
+                    }
+                    journal.rename(&staging.join(path), &target)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the directories and writes the files relative to `dir`.
+    fn write_to(&self, dir: &Path) -> Result<()> {
+        for entry in &self.entries {
+            match entry {
+                Entry::Dir(path) => {
+                    let path = dir.join(path);
+                    fs::create_dir_all(&path)
+                        .map_err(|e| Error::Io(format!("{} - {}", path.display(), e)))?
+                },
+                Entry::File(path, content) => {
+                    let path = dir.join(path);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)
+                            .map_err(|e| Error::Io(format!("{} - {}", parent.display(), e)))?;
+                    }
+                    fs::write(&path, content)
+                        .map_err(|e| Error::Io(format!("{} - {}", path.display(), e)))?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Hidden directory next to the root, e.g. `.com.e314.protect.e314-new`.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let name = self.root.file_name().unwrap_or_default().to_string_lossy();
+        self.root.with_file_name(format!(".{}.e314-{}", name, suffix))
+    }
+
+    /// Prints the planned tree with file sizes and whether each file is new, changed or
+    /// unchanged on disk, optionally followed by the contents or the diffs of the files.
+    pub fn print(&self, contents: bool, diff: bool) {
+        let mut root = Node::default();
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let (path, file) = match entry {
+                Entry::Dir(path) => (path, None),
+                Entry::File(path, _) => (path, Some(idx)),
+            };
+            let mut node = &mut root;
+            for component in path.components() {
+                node = node.children
+                    .entry(component.as_os_str().to_string_lossy().to_string())
+                    .or_default();
+            }
+            if file.is_some() {
+                node.file = file;
+            }
+        }
+
+        println!("{}", format!("{}/", self.root.display()).blue());
+        self.print_children(&root, "");
+
+        for entry in &self.entries {
+            if let Entry::File(path, content) = entry {
+                let path = self.root.join(path);
+                let existing = fs::read_to_string(&path).ok();
+                if diff && existing.as_deref().is_some_and(|existing| existing != content) {
+                    println!("\n{}", format!("--- {}", path.display()).bold());
+                    print_diff(existing.as_deref().unwrap_or_default(), content);
+                } else if contents {
+                    println!("\n{}", format!("--- {}", path.display()).bold());
+                    print!("{}", content);
+                }
+            }
+        }
+    }
+
+    fn print_children(&self, node: &Node, prefix: &str) {
+        let count = node.children.len();
+        for (idx, (name, child)) in node.children.iter().enumerate() {
+            let last = idx + 1 == count;
+            println!("{}{} {}", prefix, if last { "└──" } else { "├──" }, self.label(name, child));
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            self.print_children(child, &prefix);
+        }
+    }
+
+    fn label(&self, name: &str, node: &Node) -> String {
+        match node.file.map(|idx| &self.entries[idx]) {
+            Some(Entry::File(path, content)) => {
+                let state = match fs::read_to_string(self.root.join(path)) {
+                    Ok(existing) if existing == *content => "unchanged".dimmed(),
+                    Ok(_) => "changed".yellow(),
+                    Err(_) => "new".green(),
+                };
+                format!("{} ({} B) {}", name, content.len(), state)
+            },
+            _ => format!("{}/", name).blue().to_string(),
+        }
+    }
+}
+
+/// Path of `to` relative to the directory `from`, both relative to the same root,
+/// e.g. `../UPM/README.md` from `E314.Foo` to `UPM/README.md`.
+pub fn relative(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().filter(|c| *c != Component::CurDir).collect();
+    let to: Vec<_> = to.components().filter(|c| *c != Component::CurDir).collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+    path
+}
+
+/// Pretty JSON with two-space indentation and a trailing newline, the form of every
+/// generated JSON file.
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    serde_json::to_string_pretty(value)
+        .map(|json| json + "\n")
+        .map_err(|e| Error::Template(format!("JSON could not be generated - {}", e)))
+}
+
+/// `relative` with `/` separators, and `.` when both are the same folder.
+pub fn link(from: &Path, to: &Path) -> String {
+    let path = relative(from, to);
+    match path.as_os_str().is_empty() {
+        true => String::from("."),
+        false => path.to_string_lossy().replace('\\', "/"),
+    }
+}
+
+fn remove_dir(path: &Path) -> Result<()> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound =>
+            Err(Error::Io(format!("{} - {}", path.display(), e))),
+        _ => Ok(()),
+    }
+}
+
+/// Directories created and files moved while the staged files are moved into place, so
+/// that they can be undone in reverse order.
+#[derive(Default)]
+struct Journal {
+    steps: Vec<Step>,
+}
+
+enum Step {
+    CreatedDir(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+impl Journal {
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        let missing: Vec<&Path> = path.ancestors()
+            .take_while(|dir| !dir.as_os_str().is_empty() && dir.symlink_metadata().is_err())
+            .collect();
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(dir).map_err(|e| Error::Io(format!("{} - {}", dir.display(), e)))?;
+            self.steps.push(Step::CreatedDir(dir.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to).map_err(|e| Error::Io(format!("{} - {}", to.display(), e)))?;
+        self.steps.push(Step::Renamed { from: from.to_path_buf(), to: to.to_path_buf() });
+        Ok(())
+    }
+
+    fn undo(self) {
+        for step in self.steps.into_iter().rev() {
+            match step {
+                Step::CreatedDir(dir) => { let _ = fs::remove_dir(&dir); },
+                Step::Renamed { from, to } => { let _ = fs::rename(&to, &from); },
+            }
+        }
+    }
+}
+
+/// Prints the changed lines of a line diff with a few lines of context around them.
+fn print_diff(old: &str, new: &str) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, suffix based.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = lines.iter()
+        .enumerate()
+        .filter(|(_, (kind, _))| *kind != ' ')
+        .map(|(idx, _)| idx)
+        .collect();
+    let mut last_printed: Option<usize> = None;
+    for (idx, (kind, line)) in lines.iter().enumerate() {
+        let near_change = changed.iter().any(|changed| changed.abs_diff(idx) <= DIFF_CONTEXT);
+        if !near_change {
+            continue;
+        }
+        if last_printed.is_some_and(|last| last + 1 != idx) {
+            println!("{}", "...".dimmed());
+        }
+        match kind {
+            '+' => println!("{}", format!("+{}", line).green()),
+            '-' => println!("{}", format!("-{}", line).red()),
+            _ => println!(" {}", line),
+        }
+        last_printed = Some(idx);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::test_fixtures::TempDir;
+
+    fn module_plan(root: &Path) -> Plan {
+        let mut plan = Plan::new(root);
+        plan.add_dir("Assets");
+        plan.add_file("README.md", "new readme");
+        plan.add_file("UPM/package.json", "{}");
+        plan
+    }
+
+    /// Names in `dir`, sorted.
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn writes_a_new_root() {
+        let parent = TempDir::new("plan-new");
+        let root = parent.path().join("module");
+        module_plan(&root).apply(false).unwrap();
+
+        assert_eq!(names(parent.path()), ["module"]);
+        assert_eq!(names(&root), ["Assets", "README.md", "UPM"]);
+        assert_eq!(fs::read_to_string(root.join("UPM/package.json")).unwrap(), "{}");
+    }
+
+    #[test]
+    fn refuses_an_existing_root_without_force() {
+        let parent = TempDir::new("plan-exists");
+        let root = parent.path().join("module");
+        parent.write("module/README.md", "old readme");
+
+        let result = module_plan(&root).apply(false);
+
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert_eq!(names(&root), ["README.md"]);
+        assert_eq!(fs::read_to_string(root.join("README.md")).unwrap(), "old readme");
+    }
+
+    #[test]
+    fn force_overwrites_the_planned_files_and_keeps_the_others() {
+        let parent = TempDir::new("plan-force");
+        let root = parent.path().join("module");
+        parent.write("module/.git/HEAD", "ref: refs/heads/main");
+        parent.write("module/Library/cache", "cache");
+        parent.write("module/README.md", "old readme");
+
+        module_plan(&root).apply(true).unwrap();
+
+        assert_eq!(names(parent.path()), ["module"]);
+        assert_eq!(names(&root), [".git", "Assets", "Library", "README.md", "UPM"]);
+        assert_eq!(fs::read_to_string(root.join("README.md")).unwrap(), "new readme");
+        assert_eq!(fs::read_to_string(root.join(".git/HEAD")).unwrap(), "ref: refs/heads/main");
+        assert_eq!(fs::read_to_string(root.join("Library/cache")).unwrap(), "cache");
+    }
+
+    #[test]
+    fn force_restores_the_root_when_a_file_cannot_be_moved_into_place() {
+        let parent = TempDir::new("plan-rollback");
+        let root = parent.path().join("module");
+        parent.write("module/README.md", "old readme");
+        parent.write("module/Library/cache", "cache");
+
+        // The directory at `Library` cannot be replaced by a file, after the readme was.
+        let mut plan = module_plan(&root);
+        plan.add_file("Library", "file");
+        let result = plan.apply(true);
+
+        assert!(matches!(result, Err(Error::Io(_))));
+        assert_eq!(names(parent.path()), ["module"]);
+        assert_eq!(names(&root), ["Library", "README.md"]);
+        assert_eq!(fs::read_to_string(root.join("README.md")).unwrap(), "old readme");
+        assert_eq!(fs::read_to_string(root.join("Library/cache")).unwrap(), "cache");
+    }
+}
\ No newline at end of file
@@ -1,10 +1,80 @@
-﻿use crate::cli::repositories::{RepoInfo, Repositories};
+﻿use crate::cli::config::{Config, ModulePaths};
+use crate::cli::error::{Error, Result};
+use crate::cli::packages_manifest::PackagesManifest;
+use crate::cli::repositories::{RepoInfo, Repositories};
+use crate::cli::unity_editor::{compare_versions, split_min_version, UnityVersion};
 use crate::cli::unity_project::UnityProject;
 use colored::Colorize;
-use std::io::Write;
+use chrono::{DateTime, Datelike, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::io::{IsTerminal, Write};
 use std::io;
-use crate::cli::dot_net_project::DoNetProject;
-use crate::cli::upm::UPM;
+use std::path::Path;
+use crate::cli::meta;
+use crate::cli::plan::{link, Plan};
+use crate::cli::template::Pack;
+use crate::cli::upm::{PackageDetails, Sample, UPM};
+
+/// License of a new module; the built-in template pack has the text of only this one.
+const DEFAULT_LICENSE: &str = "MIT";
+
+/// Answers given up front through `new` flags or an answers file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Answers {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Indices, names or glob patterns of the registry modules.
+    #[serde(alias = "deps")]
+    pub dependencies: Option<Vec<String>>,
+    pub author_url: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub license: Option<String>,
+    /// Git URL of the repository of the module.
+    pub repository: Option<String>,
+    /// Oldest Unity version the package supports, e.g. `2022.3` or `2022.3.10f1`.
+    pub min_unity_version: Option<String>,
+    pub documentation_url: Option<String>,
+    pub changelog_url: Option<String>,
+    pub licenses_url: Option<String>,
+    pub samples: Option<Vec<Sample>>,
+}
+
+impl Answers {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::InvalidInput(format!("Answers file '{}' - {}", path, e)))?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| Error::InvalidInput(format!("Answers file '{}' is malformed: {}", path, e)))
+    }
+
+    /// Keeps the answers of `self` and takes the missing ones from `other`.
+    pub fn or(self, other: Answers) -> Answers {
+        Answers {
+            name: self.name.or(other.name),
+            version: self.version.or(other.version),
+            description: self.description.or(other.description),
+            author_name: self.author_name.or(other.author_name),
+            author_email: self.author_email.or(other.author_email),
+            dependencies: self.dependencies.or(other.dependencies),
+            author_url: self.author_url.or(other.author_url),
+            keywords: self.keywords.or(other.keywords),
+            license: self.license.or(other.license),
+            repository: self.repository.or(other.repository),
+            min_unity_version: self.min_unity_version.or(other.min_unity_version),
+            documentation_url: self.documentation_url.or(other.documentation_url),
+            changelog_url: self.changelog_url.or(other.changelog_url),
+            licenses_url: self.licenses_url.or(other.licenses_url),
+            samples: self.samples.or(other.samples),
+        }
+    }
+}
 
 pub struct Project {
     name: String,
@@ -14,11 +84,14 @@ pub struct Project {
     dependencies: Vec<RepoInfo>,
     author_name: String,
     author_email: String,
+    unity: UnityVersion,
+    details: PackageDetails,
     repositories: Repositories,
+    config: Config,
 }
 
 impl Project {
-    pub fn new() -> Self {
+    pub fn new(repositories: Repositories, config: Config) -> Self {
         println!("\n");
 
         Project {
@@ -29,208 +102,421 @@ impl Project {
             dependencies: Vec::new(),
             author_name: String::new(),
             author_email: String::new(),
-            repositories: Repositories::new(),
+            unity: UnityVersion { version: String::new(), revision: None },
+            details: PackageDetails::default(),
+            repositories,
+            config,
         }
     }
     
-    pub fn configure(&mut self) {
-        self.set_name();
-        self.set_version();
-        self.set_description();
-        self.set_author_name();
-        self.set_author_email();
-        self.set_dependencies();
-    }
-
-    pub fn create(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.create_upm()?;
-        self.create_unity_project()?;
-        self.create_don_net_project()?;
+    /// Fills the project from the answers, prompting for the missing ones when stdin is a terminal.
+    pub fn configure(&mut self, answers: Answers) -> Result<()> {
+        self.set_name(answers.name)?;
+        self.set_version(answers.version)?;
+        self.set_description(answers.description)?;
+        self.set_author_name(answers.author_name)?;
+        self.set_author_email(answers.author_email)?;
+        self.set_author_url(answers.author_url)?;
+        self.set_dependencies(answers.dependencies)?;
+        self.set_keywords(answers.keywords)?;
+        self.set_license(answers.license)?;
+        self.set_repository(answers.repository)?;
+        self.set_unity_version();
+        self.set_min_unity_version(answers.min_unity_version)?;
+        self.details.documentation_url = ask_web_url(answers.documentation_url, "Enter documentation URL", "Documentation URL")?;
+        self.details.changelog_url = ask_web_url(answers.changelog_url, "Enter changelog URL", "Changelog URL")?;
+        self.details.licenses_url = ask_web_url(answers.licenses_url, "Enter licenses URL", "Licenses URL")?;
+        self.set_samples(answers.samples)?;
         Ok(())
     }
+
+    /// Collects every directory and file of the project under `output` without writing anything.
+    /// The JSON files are generated in code and the text files are rendered from `pack`; a pack
+    /// file at the path of a generated one replaces it. `offline` resolves the lock file of
+    /// the Unity project from cached registry documents only.
+    pub fn plan(&self, output: &Path, pack: &Pack, offline: bool) -> Result<Plan> {
+        let paths = self.config.layout.paths(output, &self.name, &self.display_name)?;
+        let mut plan = Plan::new(&paths.root);
+        let mut context = self.context()?;
+        let upm_license = pack.renders("upm/LICENSE.md", &paths, &context)?;
+        context["license_file"] = json!(pack.renders("root/LICENSE", &paths, &context)?);
+        self.create_upm(&mut plan, &paths, upm_license)?;
+        self.create_unity_project(&mut plan, &paths, offline)?;
+        pack.render(&mut plan, &paths, &context)?;
+        meta::add_metas(&mut plan, &paths.upm, &self.name, self.config.guids);
+        Ok(plan)
+    }
+
+    /// Values the templates can use.
+    fn context(&self) -> Result<Value> {
+        let dependencies: Vec<Value> = self.dependencies.iter()
+            .map(|dep| json!({
+                "name": dep.name,
+                "version": dep.version,
+                "url": dep.url,
+            }))
+            .collect();
+        Ok(json!({
+            "name": self.name,
+            "display_name": self.display_name,
+            "version": self.version,
+            "description": self.description,
+            "author_name": self.author_name,
+            "author_email": self.author_email,
+            "year": year()?,
+            "scope": self.config.scope,
+            "prefix": self.config.prefix,
+            "dependencies": dependencies,
+            "license": self.details.license,
+            "mit_license": self.details.license == DEFAULT_LICENSE,
+            "repository": self.details.repository,
+            "unity": {
+                "version": self.unity.version,
+                "revision": self.unity.revision,
+            },
+            "tests": {
+                "editor": self.config.tests.editor(),
+                "runtime": self.config.tests.runtime(),
+            },
+        }))
+    }
     
-    fn create_upm(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn create_upm(&self, plan: &mut Plan, paths: &ModulePaths, license: bool) -> Result<()> {
         let upm = UPM::new(
             self.name.clone(),
             self.display_name.clone(),
             self.version.clone(),
             self.description.clone(),
+            self.package_details(paths, license),
             self.author_name.clone(),
             self.author_email.clone(),
-            self.dependencies.clone());
-        upm.create()?;
+            self.dependencies.clone(),
+            self.config.asmdef_references,
+            self.config.guids,
+            self.config.tests);
+        upm.create(plan, &paths.upm)?;
         Ok(())
     }
     
-    fn create_unity_project(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let uni_project = UnityProject::new(self.name.clone(), self.display_name.clone());
-        uni_project.create()?;
-        Ok(())
+    /// The details with the documentation, changelog and licenses URLs that are not given
+    /// pointing to the files of the package at the version tag, for a repository on the web.
+    /// The licenses URL is derived only when the package gets a `LICENSE.md`, per `license`.
+    fn package_details(&self, paths: &ModulePaths, license: bool) -> PackageDetails {
+        let mut details = self.details.clone();
+        let Some(base) = details.repository.as_deref()
+            .filter(|url| url.starts_with("https://"))
+            .map(|url| url.trim_end_matches('/').trim_end_matches(".git")) else {
+            return details;
+        };
+        let upm = match link(Path::new(""), &paths.upm).as_str() {
+            "." => String::new(),
+            upm => format!("{}/", upm),
+        };
+        let file_url = |file: &str| Some(format!("{}/blob/{}/{}{}", base, self.version, upm, file));
+        details.documentation_url = details.documentation_url.or_else(|| file_url("README.md"));
+        details.changelog_url = details.changelog_url.or_else(|| file_url("CHANGELOG.md"));
+        if license {
+            details.licenses_url = details.licenses_url.or_else(|| file_url("LICENSE.md"));
+        }
+        details
     }
-    
-    fn create_don_net_project(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let dot_net_project = DoNetProject::new(
-            self.name.clone(),
-            self.display_name.clone(),
-            self.version.clone(),
-            self.description.clone(),
-            self.author_name.clone(),
-            self.author_email.clone(),
-            self.dependencies.clone());
-        dot_net_project.create()?;
+
+    /// The Unity project that the package is developed in: it installs the package from
+    /// the UPM folder together with its E314 dependencies.
+    fn create_unity_project(&self, plan: &mut Plan, paths: &ModulePaths, offline: bool) -> Result<()> {
+        let mut manifest = PackagesManifest::new();
+        let upm_link = link(&paths.unity.join("Packages"), &paths.upm);
+        manifest.add_local(&self.name, &upm_link);
+        for dep in &self.dependencies {
+            manifest.add_dependency(dep, self.config.dependency_source)?;
+        }
+        for registry in &self.config.unity.scoped_registries {
+            manifest.add_scoped_registry(registry.clone());
+        }
+        for testable in &self.config.unity.testables {
+            manifest.add_testable(testable);
+        }
+        let uni_project = UnityProject::new(offline);
+        uni_project.create(plan, &paths.unity, &manifest)?;
         Ok(())
     }
 
-    fn set_name(&mut self) {
-        match read_input("Enter name: ") {
-            Ok(input_name) => {
-                if input_name.is_empty() {
-                    println!("  {} Name - {}", "✗".red(), "empty".red());
-                    self.set_name();
-                    return;
-                }
-                let name = format!("com.e314.{}", input_name.to_lowercase());
-                match self.repositories.get_repo_by_name(&name) {
-                    Ok(_) => {
-                        println!("  {} Name: {} - {}", "✗".red(), self.name, "already exists".red());
-                        self.set_name();
-                    },
-                    Err(_) => {
-                        self.name = name;
-                        self.display_name = format!("E314.{}", input_name);
-                        println!("  {} Name: {}", "✓".green(), self.name.blue());
-                        println!("  {} Display Name: {}", "✓".green(), self.display_name.blue());
-                    }
-                }
+    /// The configured editor version, or the newest installed one.
+    fn set_unity_version(&mut self) {
+        self.unity = UnityVersion::select(self.config.unity.version.as_deref());
+        println!("  {} Unity: {}", "✓".green(), self.unity.version.blue());
+    }
+
+    /// The oldest Unity version the package supports, by default the release line of the
+    /// editor of the project.
+    fn set_min_unity_version(&mut self, given: Option<String>) -> Result<()> {
+        let default = self.unity.release_line();
+        let editor = self.unity.version.clone();
+        let min_version = ask_or(given, "Enter minimum Unity version", &default, |version| {
+            if split_min_version(version).is_none() {
+                return Err(Error::InvalidInput(format!(
+                    "Minimum Unity version: {} - invalid format\n    Format: 2022.3 or 2022.3.10f1", version)));
             }
-            Err(e) => {
-                println!("  {} Name - {}", "✗".red(), e.to_string().red());
-                self.set_name();
+            if compare_versions(version, &editor).is_gt() {
+                return Err(Error::InvalidInput(format!(
+                    "Minimum Unity version: {} - newer than the editor {}", version, editor)));
             }
-        }
+            Ok(version.to_string())
+        })?;
+        (self.details.unity, self.details.unity_release) = split_min_version(&min_version).unwrap();
+        println!("  {} Minimum Unity: {}", "✓".green(), min_version.blue());
+        Ok(())
     }
-    
-    fn set_version(&mut self) {
-        match read_input("Enter version: ") {
-            Ok(version) => {
-                if is_valid_semantic_version(&version) {
-                    self.version = version.clone();
-                    println!("  {} Version: {}", "✓".green(), self.version.blue());
-                    return;
-                }
-                println!("  {} Version: {} - {}", "✗".red(), version, "invalid format".red());
-                println!("    Format X.Y.Z (non-negative integers without leading zeros)");
-                self.set_version();
+
+    fn set_name(&mut self, given: Option<String>) -> Result<()> {
+        let input_name = ask(given, "Enter name: ", "--name", |input| {
+            if input.is_empty() {
+                return Err(Error::InvalidInput(String::from("Name - empty")));
             }
-            Err(e) => {
-                println!("  {} Version - {}", "✗".red(), e.to_string().red());
-                self.set_version();
+            let (name, _) = self.config.names(input);
+            match self.repositories.get_repo_by_name(&name) {
+                Ok(_) => Err(Error::InvalidInput(format!("Name: {} - already exists", name))),
+                Err(_) => Ok(input.to_string()),
             }
-        }
+        })?;
+        (self.name, self.display_name) = self.config.names(&input_name);
+        println!("  {} Name: {}", "✓".green(), self.name.blue());
+        println!("  {} Display Name: {}", "✓".green(), self.display_name.blue());
+        Ok(())
     }
 
-    fn set_description(&mut self) {
-        match read_input("Enter description: ") {
-            Ok(input_description) => {
-                if input_description.is_empty() {
-                    println!("  {} Description - {}", "✗".red(), "empty".red());
-                    return;
-                }
-                self.description = input_description.clone();
-                println!("  {} Description: [...]", "✓".green());
-            },
-            Err(e) => {
-                println!("  {} Description - {}", "✗".red(), e.to_string().red());
-                self.set_description();
+    fn set_version(&mut self, given: Option<String>) -> Result<()> {
+        self.version = ask(given, "Enter version: ", "--version", |version| {
+            if is_valid_semantic_version(version) {
+                Ok(version.to_string())
+            } else {
+                Err(Error::InvalidInput(format!(
+                    "Version: {} - invalid format\n    Format X.Y.Z (non-negative integers without leading zeros)",
+                    version)))
             }
-        }
+        })?;
+        println!("  {} Version: {}", "✓".green(), self.version.blue());
+        Ok(())
     }
 
-    fn set_author_name(&mut self) {
-        match read_input("Enter author name: ") {
-            Ok(name) => {
-                if name.is_empty() {
-                    println!("  {} Author name - {}", "✗".red(), "empty".red());
-                    self.set_author_name();
-                    return;
-                }
-                self.author_name = name;
-                println!("  {} Author name: {}", "✓".green(), self.author_name.blue());
+    fn set_description(&mut self, given: Option<String>) -> Result<()> {
+        self.description = ask(given, "Enter description: ", "--description", |description| {
+            if description.is_empty() {
+                return Err(Error::InvalidInput(String::from("Description - empty")));
             }
-            Err(e) => {
-                println!("  {} Author name - {}", "✗".red(), e.to_string().red());
-                self.set_author_name();
-            }
-        }
+            Ok(description.to_string())
+        })?;
+        println!("  {} Description: [...]", "✓".green());
+        Ok(())
     }
-    
-    fn set_author_email(&mut self) {
-        match read_input("Enter author email: ") {
-            Ok(email) => {
-                if is_valid_email(&email) {
-                    self.author_email = email;
-                    println!("  {} Author email: {}", "✓".green(), self.author_email.blue());
-                    return;
-                }
-                println!("  {} Author email - {}", "✗".red(), "invalid format".red());
-                println!("    Format: name@example.com");
-                self.set_author_email();
+
+    fn set_author_name(&mut self, given: Option<String>) -> Result<()> {
+        self.author_name = ask(given, "Enter author name: ", "--author-name", |name| {
+            if name.is_empty() {
+                return Err(Error::InvalidInput(String::from("Author name - empty")));
             }
-            Err(e) => {
-                println!("  {} Author email - {}", "✗".red(), e.to_string().red());
-                self.set_author_email();
+            Ok(name.to_string())
+        })?;
+        println!("  {} Author name: {}", "✓".green(), self.author_name.blue());
+        Ok(())
+    }
+
+    fn set_author_email(&mut self, given: Option<String>) -> Result<()> {
+        self.author_email = ask(given, "Enter author email: ", "--author-email", |email| {
+            if is_valid_email(email) {
+                Ok(email.to_string())
+            } else {
+                Err(Error::InvalidInput(format!(
+                    "Author email: {} - invalid format\n    Format: name@example.com", email)))
             }
-        }
+        })?;
+        println!("  {} Author email: {}", "✓".green(), self.author_email.blue());
+        Ok(())
+    }
+
+    fn set_author_url(&mut self, given: Option<String>) -> Result<()> {
+        self.details.author_url = ask_web_url(given, "Enter author URL", "Author URL")?;
+        Ok(())
+    }
+
+    fn set_keywords(&mut self, given: Option<Vec<String>>) -> Result<()> {
+        let given = given.map(|keywords| keywords.join(","));
+        self.details.keywords = ask_or(given, "Enter keywords separated by commas", "", |input| {
+            Ok(input.split(',')
+                .map(str::trim)
+                .filter(|keyword| !keyword.is_empty())
+                .map(String::from)
+                .collect())
+        })?;
+        let keywords = self.details.keywords.join(", ");
+        print_optional("Keywords", Some(keywords.as_str()).filter(|keywords| !keywords.is_empty()));
+        Ok(())
     }
 
-    fn set_dependencies(&mut self) {
-        self.repositories.list();
-        match read_input("Enter idxs with a space: ") {
-            Ok(idxs) => {
-                let idx_nums: Vec<usize> = idxs.split(' ')
-                    .filter(|s| !s.is_empty())
-                    .filter_map(|s| s.parse::<usize>().ok())
-                    .collect();
-
-                for idx in idx_nums {
-                    match self.repositories.get_repo_by_idx(idx) {
-                        Ok(repo) => {
-                            if !self.dependencies.contains(repo) {
-                                self.dependencies.push(repo.clone());
-                            }
-                        }
-                        Err(_) => println!("  {} Invalid idx: {}", "✗".red(), idx.to_string().red())
-                    }
-                }
-
-                if self.dependencies.is_empty() {
-                    println!("  {} Dependencies", "✓".green());
-                } else {
-                    println!("  {} Dependencies: {}", "✓".green(),
-                             self.dependencies.iter()
-                                 .map(|repo| repo.name.clone())
-                                 .collect::<Vec<String>>()
-                                 .join(", ")
-                                 .blue());
-                }
+    fn set_license(&mut self, given: Option<String>) -> Result<()> {
+        self.details.license = ask_or(given, "Enter license", DEFAULT_LICENSE, |license| {
+            if is_valid_license(license) {
+                Ok(license.to_string())
+            } else {
+                Err(Error::InvalidInput(format!(
+                    "License: {} - invalid format\n    Format: an SPDX license expression, e.g. MIT or Apache-2.0",
+                    license)))
             }
-            Err(e) => {
-                println!("  {} Dependencies - {}", "✗".red(), e.to_string().red());
-                self.set_dependencies();
+        })?;
+        println!("  {} License: {}", "✓".green(), self.details.license.blue());
+        Ok(())
+    }
+
+    fn set_repository(&mut self, given: Option<String>) -> Result<()> {
+        self.details.repository = ask_or(given, "Enter repository URL", "", |url| {
+            if url.is_empty() {
+                Ok(None)
+            } else if is_valid_git_url(url) {
+                Ok(Some(url.to_string()))
+            } else {
+                Err(Error::InvalidInput(format!(
+                    "Repository: {} - invalid format\n    Format: https://github.com/owner/repo.git", url)))
             }
+        })?;
+        print_optional("Repository", self.details.repository.as_deref());
+        Ok(())
+    }
+
+    /// The samples given, or the folders in `Samples~` asked for, each named after its folder.
+    fn set_samples(&mut self, given: Option<Vec<Sample>>) -> Result<()> {
+        self.details.samples = match given {
+            Some(samples) => samples,
+            None => ask_or(None, "Enter sample folders separated by commas", "", |input| {
+                Ok(input.split(',')
+                    .map(str::trim)
+                    .filter(|folder| !folder.is_empty())
+                    .map(Sample::in_folder)
+                    .collect())
+            })?,
+        };
+        let paths = self.details.samples.iter()
+            .map(|sample| sample.path.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        print_optional("Samples", Some(paths.as_str()).filter(|paths| !paths.is_empty()));
+        Ok(())
+    }
+
+    fn set_dependencies(&mut self, given: Option<Vec<String>>) -> Result<()> {
+        if given.is_none() && io::stdin().is_terminal() {
+            self.repositories.list();
+        }
+        let given = given.map(|deps| deps.join(" "));
+        self.dependencies = ask(given, "Enter idxs or names with a space: ", "--deps", |input| {
+            let queries: Vec<String> = input.split([' ', ','])
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect();
+            let repos = self.repositories.resolve(&queries)?;
+            Ok(repos.into_iter().cloned().collect())
+        })?;
+
+        if self.dependencies.is_empty() {
+            println!("  {} Dependencies", "✓".green());
+        } else {
+            println!("  {} Dependencies: {}", "✓".green(),
+                     self.dependencies.iter()
+                         .map(|repo| repo.name.clone())
+                         .collect::<Vec<String>>()
+                         .join(", ")
+                         .blue());
         }
+        Ok(())
+    }
+}
+
+/// Validates the given value, or prompts for it until it is valid when stdin is a terminal.
+/// `flag` is the `new` flag of the value; the answers file key has the same name.
+fn ask<T>(given: Option<String>, prompt: &str, flag: &str, parse: impl Fn(&str) -> Result<T>) -> Result<T> {
+    if let Some(value) = given {
+        return parse(value.trim());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(Error::InvalidInput(format!(
+            "No {} given and stdin is not a terminal\n    Pass {} or set '{}' in the answers file",
+            flag, flag, flag.trim_start_matches("--").replace('-', "_"))));
     }
+    prompt_until_valid(prompt, parse)
 }
 
-fn read_input(prompt: &str) -> Result<String, io::Error> {
+fn prompt_until_valid<T>(prompt: &str, parse: impl Fn(&str) -> Result<T>) -> Result<T> {
+    loop {
+        match parse(&read_input(prompt)?) {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("  {} {}", "✗".red(), e.message().red()),
+        }
+    }
+}
+
+/// Like `ask`, but an empty answer, or none when stdin is not a terminal, takes `default`.
+fn ask_or<T>(given: Option<String>, prompt: &str, default: &str, parse: impl Fn(&str) -> Result<T>) -> Result<T> {
+    let parse = |input: &str| parse(if input.is_empty() { default } else { input });
+    if let Some(value) = given {
+        return parse(value.trim());
+    }
+    if !io::stdin().is_terminal() {
+        return parse(default);
+    }
+    let prompt = match default.is_empty() {
+        true => format!("{}: ", prompt),
+        false => format!("{} [{}]: ", prompt, default),
+    };
+    prompt_until_valid(&prompt, parse)
+}
+
+/// An optional http(s) URL, printed and reported under `label`.
+fn ask_web_url(given: Option<String>, prompt: &str, label: &str) -> Result<Option<String>> {
+    let url = ask_or(given, prompt, "", |url| {
+        if url.is_empty() {
+            Ok(None)
+        } else if is_valid_web_url(url) {
+            Ok(Some(url.to_string()))
+        } else {
+            Err(Error::InvalidInput(format!(
+                "{}: {} - invalid format\n    Format: https://example.com", label, url)))
+        }
+    })?;
+    print_optional(label, url.as_deref());
+    Ok(url)
+}
+
+/// Prints the line of an answer that may be left empty.
+fn print_optional(label: &str, value: Option<&str>) {
+    match value {
+        Some(value) => println!("  {} {}: {}", "✓".green(), label, value.blue()),
+        None => println!("  {} {}", "✓".green(), label),
+    }
+}
+
+fn read_input(prompt: &str) -> Result<String> {
     print!("{}", prompt);
     io::stdout().flush()?;
     let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(Error::InvalidInput(String::from("Input was closed before all answers were given")));
+    }
     Ok(input.trim().to_string())
 }
 
-fn is_valid_semantic_version(version: &str) -> bool {
+/// The current year, or the year of `SOURCE_DATE_EPOCH` so that generating a module
+/// again later gives the same files.
+fn year() -> Result<i32> {
+    let Ok(epoch) = env::var("SOURCE_DATE_EPOCH") else {
+        return Ok(Utc::now().year());
+    };
+    epoch.trim().parse::<i64>().ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .map(|date| date.year())
+        .ok_or_else(|| Error::InvalidInput(format!("SOURCE_DATE_EPOCH: {} - not a Unix timestamp", epoch)))
+}
+
+pub fn is_valid_semantic_version(version: &str) -> bool {
     let parts: Vec<&str> = version.split('.').collect();
     if parts.len() != 3 {
         return false
@@ -239,7 +525,7 @@ fn is_valid_semantic_version(version: &str) -> bool {
         if part.is_empty() || (part.starts_with('0') && part != "0") {
             return false
         }
-        if !part.chars().all(|c| c.is_digit(10)) {
+        if !part.chars().all(|c| c.is_ascii_digit()) {
             return false
         }
     }
@@ -251,3 +537,21 @@ fn is_valid_email(email: &str) -> bool {
         .unwrap();
     email_regex.is_match(email)
 }
+
+
+fn is_valid_web_url(url: &str) -> bool {
+    let url_regex = regex::Regex::new(r"^https?://[^\s/]+\S*$").unwrap();
+    url_regex.is_match(url)
+}
+
+/// An http(s), ssh or git URL, or an scp-like address such as `git@github.com:owner/repo.git`.
+fn is_valid_git_url(url: &str) -> bool {
+    let url_regex = regex::Regex::new(r"^((https?|ssh|git)://[^\s/]+\S*|[\w.-]+@[\w.-]+:\S+)$").unwrap();
+    url_regex.is_match(url)
+}
+
+/// An SPDX license identifier or an expression of them, e.g. `MIT OR Apache-2.0`.
+fn is_valid_license(license: &str) -> bool {
+    let license_regex = regex::Regex::new(r"^\(?[A-Za-z0-9.+-]+\)?( (AND|OR|WITH) \(?[A-Za-z0-9.+-]+\)?)*$").unwrap();
+    license_regex.is_match(license)
+}
\ No newline at end of file
@@ -1,19 +1,161 @@
-﻿use colored::*;
-use std::process::{Command, Output};
+﻿use crate::cli::config::user_config_dir;
+use crate::cli::error::{Error, Result};
+use crate::cli::git;
+use crate::cli::meta;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
-#[derive(Debug, Clone, PartialEq)]
+const LOCAL_REGISTRY_FILE: &str = "e314.yaml";
+const USER_REGISTRY_FILE: &str = "registry.yaml";
+const DEFAULT_TAG_PATTERNS: [&str; 2] = ["{version}", "v{version}"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RepoInfo {
-    pub url: String,
     pub name: String,
     pub version: String,
+    pub url: String,
+    /// Tag naming of the repository, e.g. `v{version}`. Both `{version}` and `v{version}`
+    /// are tried when it is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Assemblies::is_empty")]
+    pub assemblies: Assemblies,
+}
+
+/// Assembly definitions of a module that the assemblies of other modules reference.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Assemblies {
+    /// Name of the runtime assembly, derived from the module name when not set,
+    /// e.g. `E314.Exceptions` for `com.e314.exceptions`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    /// GUID of the runtime `.asmdef`, for `GUID:` references.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_guid: Option<String>,
+    /// Name of the editor assembly. Editor assemblies are only referenced when it is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor_guid: Option<String>,
+}
+
+impl Assemblies {
+    fn is_empty(&self) -> bool {
+        *self == Assemblies::default()
+    }
+}
+
+impl RepoInfo {
+    /// The directory `git clone` creates for the repository.
+    pub fn dir_name(&self) -> String {
+        let url = self.url.trim_end_matches('/');
+        let name = url.rsplit(['/', '\\', ':']).next().unwrap_or(url);
+        name.trim_end_matches(".git").to_string()
+    }
+
+    /// Picks the tag of the registered version from full ref names such as `refs/tags/1.1.2`.
+    pub fn find_version_tag(&self, refs: &[String]) -> Result<String> {
+        let candidates = self.tag_candidates();
+        candidates.iter()
+            .find(|tag| refs.contains(&format!("refs/tags/{}", tag)))
+            .cloned()
+            .ok_or_else(|| Error::Git(format!("Tag for version {} was not found (tried: {})",
+                                              self.version, candidates.join(", "))))
+    }
+
+    /// Git URL Unity Package Manager installs the registered version from, e.g.
+    /// `https://github.com/epishev-m/e314-exceptions.git?path=UPM#1.1.2`. Without a
+    /// `tag_pattern` the tag is the one of the candidates the repository has, the way
+    /// `clone` finds it.
+    pub fn package_url(&self) -> Result<String> {
+        let tag = match &self.tag_pattern {
+            Some(_) => self.tag_candidates().remove(0),
+            None => git::ls_remote(&self.url, &["--tags"])
+                .and_then(|refs| self.find_version_tag(&refs))
+                .map_err(|e| Error::Git(format!("{} - {}", self.name, e.message())))?,
+        };
+        Ok(format!("{}?path=UPM#{}", self.url, tag))
+    }
+
+    pub fn runtime_assembly(&self) -> String {
+        self.assemblies.runtime.clone().unwrap_or_else(|| {
+            self.name.split('.')
+                .skip(1)
+                .map(|segment| segment.split(['-', '_']).map(capitalize).collect::<String>())
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+    }
+
+    fn tag_candidates(&self) -> Vec<String> {
+        match &self.tag_pattern {
+            Some(pattern) => vec![pattern.replace("{version}", &self.version)],
+            None => DEFAULT_TAG_PATTERNS.iter()
+                .map(|pattern| pattern.replace("{version}", &self.version))
+                .collect(),
+        }
+    }
+}
+
+/// Overrides of the version checked out by `clone`.
+#[derive(Debug, Default)]
+pub struct CloneOptions {
+    /// Branch, tag or commit to check out instead of the registered version.
+    pub git_ref: Option<String>,
+    pub depth: Option<u32>,
+    /// Number of repositories cloned at the same time.
+    pub jobs: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    modules: Option<Vec<RepoInfo>>,
 }
 
 #[derive(Debug)]
 pub struct Repositories {
     repositories: Vec<RepoInfo>,
+    source: Option<PathBuf>,
 }
 
 impl Repositories {
+    /// Loads the registry from `path` if given, otherwise from the first existing file of
+    /// `./e314.yaml` and `~/.config/e314/registry.yaml`, falling back to the built-in list.
+    pub fn load(path: Option<&String>) -> Result<Self> {
+        let mut candidates = vec![PathBuf::from(LOCAL_REGISTRY_FILE)];
+        if let Some(dir) = user_config_dir() {
+            candidates.push(dir.join(USER_REGISTRY_FILE));
+        }
+        Self::load_from(path.map(Path::new), &candidates)
+    }
+
+    /// Loads the registry from `path` if given, otherwise from the first of `candidates`
+    /// that exists and has a `modules` list, falling back to the built-in list.
+    fn load_from(path: Option<&Path>, candidates: &[PathBuf]) -> Result<Self> {
+        if let Some(path) = path {
+            return match read_registry(path)? {
+                Some(repositories) => Ok(Repositories { repositories, source: Some(path.to_path_buf()) }),
+                None => Err(Error::Registry(format!("Registry file '{}' has no 'modules' list",
+                                                    path.display()))),
+            };
+        }
+
+        for candidate in candidates.iter().filter(|path| path.is_file()) {
+            if let Some(repositories) = read_registry(candidate)? {
+                return Ok(Repositories { repositories, source: Some(candidate.clone()) });
+            }
+        }
+
+        Ok(Repositories::new())
+    }
+
     pub fn new() -> Self {
         Repositories {
             repositories: vec![
@@ -21,77 +163,588 @@ impl Repositories {
                     url: String::from("https://github.com/epishev-m/e314-exceptions.git"),
                     name: String::from("com.e314.exceptions"),
                     version: String::from("1.1.2"),
+                    tag_pattern: None,
+                    assemblies: Assemblies::default(),
                 },
                 RepoInfo {
                     url: String::from("https://github.com/epishev-m/e314-protect.git"),
                     name: String::from("com.e314.protect"),
                     version: String::from("2.1.1"),
+                    tag_pattern: None,
+                    assemblies: Assemblies::default(),
                 },
-            ]
+            ],
+            source: None,
         }
     }
 
-    pub fn get_repo_by_name(&self, name: &str) -> Result<&RepoInfo, String> {
+    /// Writes the registry back to the file it was loaded from, or to
+    /// `~/.config/e314/registry.yaml` when the built-in list was used.
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = match &self.source {
+            Some(path) => path.clone(),
+            None => user_config_dir()
+                .map(|dir| dir.join(USER_REGISTRY_FILE))
+                .ok_or_else(|| Error::Registry(String::from("Home directory was not found")))?,
+        };
+        write_registry(&path, &self.repositories)?;
+        Ok(path)
+    }
+
+    pub fn add(&mut self, repo: RepoInfo) -> Result<()> {
+        if self.get_repo_by_name(&repo.name).is_ok() {
+            return Err(Error::Registry(format!("Repository named '{}' already exists", repo.name)));
+        }
+        self.repositories.push(repo);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<RepoInfo> {
+        let idx = self.get_idx_by_name(name)?;
+        Ok(self.repositories.remove(idx))
+    }
+
+    pub fn get_repo_by_name_mut(&mut self, name: &str) -> Result<&mut RepoInfo> {
+        let idx = self.get_idx_by_name(name)?;
+        Ok(&mut self.repositories[idx])
+    }
+
+    fn get_idx_by_name(&self, name: &str) -> Result<usize> {
+        self.repositories
+            .iter()
+            .position(|repo| repo.name == name)
+            .ok_or_else(|| Error::Registry(format!("Repository named '{}' was not found", name)))
+    }
+
+    pub fn get_repo_by_name(&self, name: &str) -> Result<&RepoInfo> {
         self.repositories
             .iter()
             .find(|repo| repo.name == name)
-            .ok_or_else(|| format!("{} Repository named '{}' was not found", "✗".red(), name))
+            .ok_or_else(|| Error::Registry(format!("Repository named '{}' was not found", name)))
     }
 
-    pub fn get_repo_by_idx(&self, idx: usize) -> Result<&RepoInfo, String> {
+    pub fn get_repo_by_idx(&self, idx: usize) -> Result<&RepoInfo> {
         if idx >= self.repositories.len() {
-            Err(format!("{} Idx {} is out of bounds", "✗".red(), idx))
+            Err(Error::Registry(format!("Idx {} is out of bounds", idx)))
         } else {
             Ok(&self.repositories[idx])
         }
     }
 
+    /// Resolves module indices, full or short names (without the `com.e314.` scope) and
+    /// glob patterns such as `com.e314.*` into registry entries, without duplicates.
+    pub fn resolve(&self, queries: &[String]) -> Result<Vec<&RepoInfo>> {
+        let mut repos: Vec<&RepoInfo> = Vec::new();
+        for query in queries {
+            for repo in self.resolve_one(query)? {
+                if !repos.contains(&repo) {
+                    repos.push(repo);
+                }
+            }
+        }
+        Ok(repos)
+    }
+
+    /// Resolves one index, name or glob pattern, as `resolve` does. A short name matching
+    /// several modules is an invalid input error.
+    pub fn resolve_one(&self, query: &str) -> Result<Vec<&RepoInfo>> {
+        if let Ok(idx) = query.parse::<usize>() {
+            return Ok(vec![self.get_repo_by_idx(idx)?]);
+        }
+
+        if query.contains(['*', '?']) {
+            let pattern = glob_to_regex(query)?;
+            let repos: Vec<&RepoInfo> = self.repositories
+                .iter()
+                .filter(|repo| pattern.is_match(&repo.name) || pattern.is_match(short_name(&repo.name)))
+                .collect();
+            if repos.is_empty() {
+                return Err(Error::Registry(format!("No modules match '{}'", query)));
+            }
+            return Ok(repos);
+        }
+
+        if let Ok(repo) = self.get_repo_by_name(query) {
+            return Ok(vec![repo]);
+        }
+
+        let suffix = format!(".{}", query);
+        let repos: Vec<&RepoInfo> = self.repositories
+            .iter()
+            .filter(|repo| repo.name.ends_with(&suffix))
+            .collect();
+        match repos.len() {
+            0 => Err(Error::Registry(format!("Repository named '{}' was not found", query))),
+            1 => Ok(repos),
+            _ => Err(Error::InvalidInput(format!("Name '{}' is ambiguous: {}", query,
+                                                 repos.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>().join(", ")))),
+        }
+    }
+
     pub fn list(&self) {
-        eprintln!("Modules:");
+        println!("Modules:");
         for (index, repo) in self.repositories.iter().enumerate() {
             println!("  [{}] {} - {} - {}", index, repo.name, repo.version, repo.url);
         }
     }
 
-    pub fn clone_by_index(&self, index: usize) -> Result<(), String> {
-        if index >= self.repositories.len() {
-            return Err(format!("{} Index {} is out of repositories list bounds", "✗".red(), index));
+    pub fn clone_all(&self, options: &CloneOptions) -> Vec<Result<()>> {
+        clone_repos(&self.all(), options)
+    }
+
+    pub fn all(&self) -> Vec<&RepoInfo> {
+        self.repositories.iter().collect()
+    }
+}
+
+enum CloneEvent {
+    Cloning(usize),
+    Finished(usize, Result<String>),
+}
+
+/// Clones the repositories on `options.jobs` worker threads, printing the progress of each one
+/// and a summary table. A failed clone does not stop the others.
+pub fn clone_repos(repos: &[&RepoInfo], options: &CloneOptions) -> Vec<Result<()>> {
+    for repo in repos {
+        println!("  {} {} - queued", "…".dimmed(), repo.name);
+    }
+
+    let mut results: Vec<Option<Result<String>>> = (0..repos.len()).map(|_| None).collect();
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let jobs = options.jobs.clamp(1, repos.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    if idx >= repos.len() {
+                        break;
+                    }
+                    let _ = sender.send(CloneEvent::Cloning(idx));
+                    let result = clone_repo(repos[idx], options);
+                    let _ = sender.send(CloneEvent::Finished(idx, result));
+                }
+            });
         }
+        drop(sender);
 
-        let repo = &self.repositories[index];
+        for event in receiver {
+            match event {
+                CloneEvent::Cloning(idx) => {
+                    println!("  {} {} - cloning", "↻".yellow(), repos[idx].name);
+                },
+                CloneEvent::Finished(idx, result) => {
+                    match &result {
+                        Ok(git_ref) => println!("  {} {} - done ({})", "✓".green(), repos[idx].name, git_ref),
+                        Err(e) => println!("  {} {} - failed\n{}", "✗".red(), repos[idx].name, indent(e.message())),
+                    }
+                    results[idx] = Some(result);
+                },
+            }
+        }
+    });
+
+    let results: Vec<Result<String>> = results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(Error::Git(String::from("Clone was not started")))))
+        .collect();
+    print_summary(repos, &results);
+
+    results.into_iter()
+        .map(|result| result.map(|_| ()))
+        .collect()
+}
 
-        let output = Command::new("git")
-            .args(["clone", &repo.url])
-            .output()
-            .map_err(|e| format!("Error running git: {}", e))?;
+fn print_summary(repos: &[&RepoInfo], results: &[Result<String>]) {
+    let name_width = repos.iter().map(|repo| repo.name.len()).chain([6]).max().unwrap_or(0);
+    let ref_width = results.iter()
+        .map(|result| result.as_ref().map_or(1, |git_ref| git_ref.len()))
+        .chain([3])
+        .max()
+        .unwrap_or(0);
 
-        get_result(output, repo)
+    println!("\nSummary:");
+    println!("  {:<name_width$}  {:<ref_width$}  Result", "Module", "Ref");
+    for (repo, result) in repos.iter().zip(results) {
+        match result {
+            Ok(git_ref) => println!("  {:<name_width$}  {:<ref_width$}  {}", repo.name, git_ref, "✓ done".green()),
+            Err(_) => println!("  {:<name_width$}  {:<ref_width$}  {}", repo.name, "-", "✗ failed".red()),
+        }
     }
+}
+
+pub fn indent(text: &str) -> String {
+    text.trim_end()
+        .lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Clones the repository and returns the checked out ref.
+fn clone_repo(repo: &RepoInfo, options: &CloneOptions) -> Result<String> {
+    let refs = git::ls_remote(&repo.url, &["--heads", "--tags"])?;
+    let git_ref = match &options.git_ref {
+        Some(git_ref) => git_ref.clone(),
+        None => repo.find_version_tag(&refs)?,
+    };
 
-    pub fn clone_all(&self) -> Vec<Result<(), String>> {
-        let mut results = Vec::new();
+    let is_named_ref = refs.iter().any(|name| {
+        *name == format!("refs/heads/{}", git_ref) || *name == format!("refs/tags/{}", git_ref)
+    });
+    let dir = repo.dir_name();
+    let depth = options.depth.map(|depth| depth.to_string());
 
-        for (_, repo) in self.repositories.iter().enumerate() {
-            let output: std::io::Result<Output> = Command::new("git")
-                .args(["clone", &repo.url])
-                .output();
+    let mut args = vec!["clone"];
+    if is_named_ref {
+        args.extend(["--branch", git_ref.as_str()]);
+    } else {
+        args.push("--no-checkout");
+    }
+    if let Some(depth) = &depth {
+        args.extend(["--depth", depth.as_str()]);
+    }
+    args.extend([repo.url.as_str(), dir.as_str()]);
 
-            let result = get_result(output.unwrap(), repo);
-            results.push(result);
+    get_result(git::run(None, &args)?)?;
+    if !is_named_ref {
+        // A commit hash can't be passed to `--branch`, so it is checked out after cloning.
+        // A clone that can't check it out is removed, so that cloning again can succeed.
+        if let Err(e) = checkout_commit(Path::new(&dir), &git_ref, depth.as_deref()) {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
         }
+    }
+    Ok(git_ref)
+}
 
-        results
+fn checkout_commit(dir: &Path, git_ref: &str, depth: Option<&str>) -> Result<()> {
+    if let Some(depth) = depth {
+        get_result(git::run(Some(dir), &["fetch", "--depth", depth, "origin", git_ref])?)?;
     }
+    get_result(git::run(Some(dir), &["checkout", git_ref])?)
 }
 
-fn get_result(output: Output, repo: &RepoInfo) -> Result<(), String> {
-    let result: Result<(), String> = if output.status.success() {
-        println!("  {} {}", "✓".green(), repo.name);
-        Ok(())
+/// The name of a module without its reverse-domain scope, e.g. `protect`.
+fn short_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// A pattern that matches a module name whole; it is tried on the full name and on the
+/// name without the scope.
+fn glob_to_regex(pattern: &str) -> Result<regex::Regex> {
+    let mut expression = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            _ => expression.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expression.push('$');
+    regex::Regex::new(&expression)
+        .map_err(|e| Error::InvalidInput(format!("Pattern '{}' - {}", pattern, e)))
+}
+
+fn read_registry(path: &Path) -> Result<Option<Vec<RepoInfo>>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Registry(format!("Registry file '{}' - {}", path.display(), e)))?;
+
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let registry: RegistryFile = if is_json {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    } else {
+        serde_yaml::from_str(&content).map_err(|e| e.to_string())
+    }.map_err(|e| Error::Registry(format!("Registry file '{}' is malformed: {}", path.display(), e)))?;
+
+    Ok(registry.modules)
+}
+
+/// Reads `UPM/package.json` of the repository at `url` to build its registry entry.
+pub fn fetch_repo_info(url: &str) -> Result<RepoInfo> {
+    let tmp_path = env::temp_dir().join(format!("e314-registry-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp_path);
+
+    let tmp_dir = tmp_path.to_string_lossy();
+    let output = git::run(None, &["clone", "--depth", "1", url, &tmp_dir])?;
+
+    let result = if output.status.success() {
+        read_package_info(&tmp_path.join("UPM").join("package.json"), url)
     } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        println!("  {} {}\n    {}", "✗".red(), repo.name, error);
-        Err(String::from(""))
+        Err(Error::Git(format!("{} - {}", url, String::from_utf8_lossy(&output.stderr).trim())))
     };
+
+    let _ = fs::remove_dir_all(&tmp_path);
     result
+}
+
+fn read_package_info(path: &Path, url: &str) -> Result<RepoInfo> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Registry(format!("{} - UPM/package.json: {}", url, e)))?;
+    let package: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| Error::Registry(format!("{} - UPM/package.json is malformed: {}", url, e)))?;
+
+    let field = |key: &str| package[key]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| Error::Registry(format!("{} - UPM/package.json has no '{}'", url, key)));
+
+    let upm_path = path.parent().unwrap_or(Path::new(""));
+    let (runtime, runtime_guid) = read_asmdef(&upm_path.join("Runtime"));
+    let (editor, editor_guid) = read_asmdef(&upm_path.join("Editor"));
+    Ok(RepoInfo {
+        url: url.to_string(),
+        name: field("name")?,
+        version: field("version")?,
+        tag_pattern: None,
+        assemblies: Assemblies { runtime, runtime_guid, editor, editor_guid },
+    })
+}
+
+/// Name and GUID of the first `.asmdef` in `dir`, if there is one.
+fn read_asmdef(dir: &Path) -> (Option<String>, Option<String>) {
+    let asmdef = fs::read_dir(dir).ok()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "asmdef"))
+        .min();
+    let Some(asmdef) = asmdef else {
+        return (None, None);
+    };
+    let name = fs::read_to_string(&asmdef).ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|value| value["name"].as_str().map(String::from));
+    let guid = fs::read_to_string(asmdef.with_extension("asmdef.meta")).ok()
+        .and_then(|content| meta::parse_guid(&content));
+    (name, guid)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn write_registry(path: &Path, repositories: &[RepoInfo]) -> Result<()> {
+    let to_error = |e: String| Error::Registry(format!("Registry file '{}' - {}", path.display(), e));
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    let existing = fs::read_to_string(path).ok();
+    let content = if is_json {
+        let mut registry: serde_json::Value = match existing {
+            Some(content) => serde_json::from_str(&content).map_err(|e| to_error(e.to_string()))?,
+            None => serde_json::json!({}),
+        };
+        registry["modules"] = serde_json::to_value(repositories).map_err(|e| to_error(e.to_string()))?;
+        serde_json::to_string_pretty(&registry).map_err(|e| to_error(e.to_string()))?
+    } else {
+        let mut registry: serde_yaml::Value = match existing {
+            Some(content) => serde_yaml::from_str(&content).map_err(|e| to_error(e.to_string()))?,
+            None => serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
+        };
+        registry["modules"] = serde_yaml::to_value(repositories).map_err(|e| to_error(e.to_string()))?;
+        serde_yaml::to_string(&registry).map_err(|e| to_error(e.to_string()))?
+    };
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| to_error(e.to_string()))?;
+    }
+    fs::write(path, content).map_err(|e| to_error(e.to_string()))
+}
+
+pub fn get_result(output: Output) -> Result<()> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Git(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::test_fixtures::TempDir;
+
+    fn repo(version: &str, tag_pattern: Option<&str>) -> RepoInfo {
+        RepoInfo {
+            name: String::from("com.e314.foo"),
+            version: version.to_string(),
+            url: String::from("https://example.com/e314-foo.git"),
+            tag_pattern: tag_pattern.map(String::from),
+            assemblies: Assemblies::default(),
+        }
+    }
+
+    /// A repository in a test folder with one commit tagged with each of `tags`.
+    fn tagged_repository(dir: &TempDir, tags: &[&str]) -> String {
+        let path = dir.path().join("e314-foo");
+        let git = |args: &[&str]| assert!(git::run(Some(&path), args).unwrap().status.success(), "git {:?}", args);
+        fs::create_dir_all(&path).unwrap();
+        git(&["init", "--quiet"]);
+        git(&["-c", "user.name=Test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false",
+            "commit", "--quiet", "--allow-empty", "-m", "Initial"]);
+        for tag in tags {
+            git(&["tag", tag]);
+        }
+        path.to_string_lossy().into_owned()
+    }
+
+    fn repositories(names: &[&str]) -> Repositories {
+        let repositories = names.iter()
+            .map(|name| RepoInfo {
+                name: name.to_string(),
+                version: String::from("1.0.0"),
+                url: format!("https://example.com/{}.git", name),
+                tag_pattern: None,
+                assemblies: Assemblies::default(),
+            })
+            .collect();
+        Repositories { repositories, source: None }
+    }
+
+    fn resolve(repositories: &Repositories, queries: &[&str]) -> Result<Vec<String>> {
+        let queries: Vec<String> = queries.iter().map(|query| query.to_string()).collect();
+        Ok(repositories.resolve(&queries)?.into_iter().map(|repo| repo.name.clone()).collect())
+    }
+
+    #[test]
+    fn resolves_indices_and_names() {
+        let repos = repositories(&["com.e314.exceptions", "com.e314.protect", "com.e314.events"]);
+        assert_eq!(resolve(&repos, &["1"]).unwrap(), ["com.e314.protect"]);
+        assert_eq!(resolve(&repos, &["com.e314.events"]).unwrap(), ["com.e314.events"]);
+        assert_eq!(resolve(&repos, &["exceptions"]).unwrap(), ["com.e314.exceptions"]);
+        assert_eq!(resolve(&repos, &["protect", "1", "com.e314.protect"]).unwrap(), ["com.e314.protect"]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_out_of_bounds_queries() {
+        let repos = repositories(&["com.e314.exceptions"]);
+        assert!(matches!(resolve(&repos, &["3"]), Err(Error::Registry(_))));
+        assert!(matches!(resolve(&repos, &["protect"]), Err(Error::Registry(_))));
+        assert!(matches!(resolve(&repos, &["p*"]), Err(Error::Registry(_))));
+    }
+
+    #[test]
+    fn rejects_ambiguous_short_names() {
+        let repos = repositories(&["com.e314.protect", "com.other.protect"]);
+        assert!(matches!(resolve(&repos, &["protect"]), Err(Error::InvalidInput(_))));
+        assert_eq!(resolve(&repos, &["com.other.protect"]).unwrap(), ["com.other.protect"]);
+    }
+
+    #[test]
+    fn globs_match_the_full_name_or_the_name_without_scope() {
+        let repos = repositories(&["com.e314.exceptions", "com.e314.protect", "com.e314.events"]);
+        assert_eq!(resolve(&repos, &["e*"]).unwrap(), ["com.e314.exceptions", "com.e314.events"]);
+        assert_eq!(resolve(&repos, &["com.e314.*"]).unwrap(),
+                   ["com.e314.exceptions", "com.e314.protect", "com.e314.events"]);
+        assert_eq!(resolve(&repos, &["pro?ect"]).unwrap(), ["com.e314.protect"]);
+        assert!(resolve(&repos, &["e314*"]).is_err());
+    }
+
+    #[test]
+    fn the_package_url_has_the_tag_the_repository_has() {
+        let dir = TempDir::new("repositories-package-url");
+        let url = tagged_repository(&dir, &["v1.2.0"]);
+        let module = RepoInfo { url: url.clone(), ..repo("1.2.0", None) };
+        assert_eq!(module.package_url().unwrap(), format!("{}?path=UPM#v1.2.0", url));
+
+        let missing = RepoInfo { url, ..repo("1.3.0", None) };
+        assert!(matches!(missing.package_url(), Err(Error::Git(message)) if message.contains("1.3.0")));
+    }
+
+    #[test]
+    fn the_package_url_of_a_tag_pattern_needs_no_lookup() {
+        assert_eq!(repo("1.2.0", Some("release-{version}")).package_url().unwrap(),
+                   "https://example.com/e314-foo.git?path=UPM#release-1.2.0");
+    }
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| format!("refs/tags/{}", name)).collect()
+    }
+
+    #[test]
+    fn the_default_tags_are_the_version_then_v_and_the_version() {
+        assert_eq!(repo("1.2.0", None).tag_candidates(), ["1.2.0", "v1.2.0"]);
+        assert_eq!(repo("1.2.0", None).find_version_tag(&tags(&["v1.2.0", "1.2.0"])).unwrap(), "1.2.0");
+        assert_eq!(repo("1.2.0", None).find_version_tag(&tags(&["1.1.0", "v1.2.0"])).unwrap(), "v1.2.0");
+    }
+
+    #[test]
+    fn a_tag_pattern_replaces_the_default_tags() {
+        let module = repo("1.2.0", Some("release/{version}"));
+        assert_eq!(module.tag_candidates(), ["release/1.2.0"]);
+        assert_eq!(module.find_version_tag(&tags(&["1.2.0", "release/1.2.0"])).unwrap(), "release/1.2.0");
+        assert!(module.find_version_tag(&tags(&["1.2.0", "v1.2.0"])).is_err());
+    }
+
+    #[test]
+    fn a_missing_tag_names_the_version_and_the_tags_tried() {
+        let refs = [tags(&["1.1.0"]), vec![String::from("refs/heads/1.2.0")]].concat();
+        match repo("1.2.0", None).find_version_tag(&refs) {
+            Err(Error::Git(message)) => assert_eq!(message, "Tag for version 1.2.0 was not found (tried: 1.2.0, v1.2.0)"),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    const LOCAL_REGISTRY: &str = "modules:\n  - name: com.e314.local\n    version: 1.0.0\n    url: https://example.com/local.git\n";
+    const USER_REGISTRY: &str = "modules:\n  - name: com.e314.user\n    version: 1.0.0\n    url: https://example.com/user.git\n";
+
+    fn names(repositories: &Repositories) -> Vec<&str> {
+        repositories.repositories.iter().map(|repo| repo.name.as_str()).collect()
+    }
+
+    #[test]
+    fn the_given_registry_comes_before_the_local_and_the_user_one() {
+        let dir = TempDir::new("registry-given");
+        let given = dir.write("modules.json",
+            r#"{ "modules": [{ "name": "com.e314.given", "version": "1.0.0", "url": "https://example.com/given.git" }] }"#);
+        let candidates = [dir.write("e314.yaml", LOCAL_REGISTRY), dir.write("registry.yaml", USER_REGISTRY)];
+
+        let repositories = Repositories::load_from(Some(&given), &candidates).unwrap();
+
+        assert_eq!(names(&repositories), ["com.e314.given"]);
+        assert_eq!(repositories.source, Some(given));
+    }
+
+    #[test]
+    fn the_local_registry_comes_before_the_user_one_and_the_built_in_list() {
+        let dir = TempDir::new("registry-candidates");
+        let local = dir.path().join("e314.yaml");
+        let user = dir.write("registry.yaml", USER_REGISTRY);
+        let candidates = [local.clone(), user.clone()];
+
+        assert_eq!(names(&Repositories::load_from(None, &candidates).unwrap()), ["com.e314.user"]);
+        // A local file without modules, e.g. only settings, is passed over.
+        dir.write("e314.yaml", "scope: com.e314\n");
+        assert_eq!(names(&Repositories::load_from(None, &candidates).unwrap()), ["com.e314.user"]);
+        dir.write("e314.yaml", LOCAL_REGISTRY);
+        assert_eq!(names(&Repositories::load_from(None, &candidates).unwrap()), ["com.e314.local"]);
+
+        fs::remove_file(&local).unwrap();
+        fs::remove_file(&user).unwrap();
+        let built_in = Repositories::load_from(None, &candidates).unwrap();
+        assert_eq!(names(&built_in), ["com.e314.exceptions", "com.e314.protect"]);
+        assert_eq!(built_in.source, None);
+    }
+
+    #[test]
+    fn a_malformed_registry_file_is_an_error() {
+        let dir = TempDir::new("registry-malformed");
+        let malformed = dir.write("e314.yaml", "modules:\n  - name: [com.e314.broken\n");
+        let candidates = [malformed.clone()];
+
+        assert!(matches!(Repositories::load_from(None, &candidates),
+            Err(Error::Registry(message)) if message.contains("is malformed")));
+        assert!(matches!(Repositories::load_from(Some(&malformed), &[]), Err(Error::Registry(_))));
+        let without_modules = dir.write("settings.yaml", "scope: com.e314\n");
+        assert!(matches!(Repositories::load_from(Some(&without_modules), &[]),
+            Err(Error::Registry(message)) if message.contains("has no 'modules' list")));
+    }
 }
\ No newline at end of file
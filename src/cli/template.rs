@@ -0,0 +1,430 @@
+﻿use crate::cli::config::{user_config_dir, ModulePaths};
+use crate::cli::error::{Error, Result};
+use crate::cli::plan::{link, Plan};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Top-level folders of a pack, each rendered into the layout folder of the same name.
+const PARTS: [&str; 4] = ["root", "upm", "unity", "dotnet"];
+
+/// The files `new` generates when no template is given.
+const DEFAULT_PACK: [(&str, &str); 18] = [
+    ("root/.gitignore", include_str!("../../templates/default/root/.gitignore")),
+    ("root/LICENSE", include_str!("../../templates/default/root/LICENSE")),
+    ("root/README.md", include_str!("../../templates/default/root/README.md")),
+    ("upm/CHANGELOG.md", include_str!("../../templates/default/upm/CHANGELOG.md")),
+    ("upm/LICENSE.md", include_str!("../../templates/default/upm/LICENSE.md")),
+    ("upm/README.md", include_str!("../../templates/default/upm/README.md")),
+    ("upm/Documentation~/index.md", include_str!("../../templates/default/upm/Documentation~/index.md")),
+    ("upm/Documentation~/installation-en.md",
+     include_str!("../../templates/default/upm/Documentation~/installation-en.md")),
+    ("upm/Documentation~/installation-ru.md",
+     include_str!("../../templates/default/upm/Documentation~/installation-ru.md")),
+    ("upm/Documentation~/instructions-en.md",
+     include_str!("../../templates/default/upm/Documentation~/instructions-en.md")),
+    ("upm/Documentation~/instructions-ru.md",
+     include_str!("../../templates/default/upm/Documentation~/instructions-ru.md")),
+    ("upm/Tests/Editor/SampleEditorTests.cs",
+     include_str!("../../templates/default/upm/Tests/Editor/SampleEditorTests.cs")),
+    ("upm/Tests/Runtime/SampleRuntimeTests.cs",
+     include_str!("../../templates/default/upm/Tests/Runtime/SampleRuntimeTests.cs")),
+    ("unity/.gitignore", include_str!("../../templates/default/unity/.gitignore")),
+    ("unity/ProjectSettings/ProjectVersion.txt",
+     include_str!("../../templates/default/unity/ProjectSettings/ProjectVersion.txt")),
+    ("dotnet/{{display_name}}.sln", include_str!("../../templates/default/dotnet/{{display_name}}.sln")),
+    ("dotnet/{{display_name}}/{{display_name}}.csproj",
+     include_str!("../../templates/default/dotnet/{{display_name}}/{{display_name}}.csproj")),
+    ("dotnet/{{display_name}}.Tests/{{display_name}}.Tests.csproj",
+     include_str!("../../templates/default/dotnet/{{display_name}}.Tests/{{display_name}}.Tests.csproj")),
+];
+
+/// Text files of a module keyed by `<part>/<path>`. Paths are templates as well.
+pub struct Pack {
+    files: BTreeMap<String, String>,
+}
+
+enum Token {
+    Text(String),
+    Tag(String, usize),
+}
+
+enum Node {
+    Text(String),
+    Var { path: String, filters: Vec<String>, line: usize },
+    If { path: String, negate: bool, line: usize, then: Vec<Node>, otherwise: Vec<Node> },
+    Each { path: String, line: usize, body: Vec<Node> },
+}
+
+/// Parsed nodes and the tag that ended them with its line.
+type Block = (Vec<Node>, Option<(String, usize)>);
+
+struct Scope {
+    value: Value,
+    index: Option<(usize, usize)>,
+}
+
+impl Pack {
+    /// The built-in pack with the files of `template` added over it. `template` is a
+    /// directory or the name of one in `~/.config/e314/templates`.
+    pub fn load(template: Option<&str>) -> Result<Self> {
+        let mut files: BTreeMap<String, String> = DEFAULT_PACK.iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect();
+        if let Some(template) = template {
+            let dir = find_pack(template)?;
+            read_pack(&dir, &dir, &mut files)?;
+        }
+        Ok(Pack { files })
+    }
+
+    /// Renders every file into the layout folder of its part. Besides `context`, each file
+    /// gets `paths.root`, `paths.upm`, `paths.unity` and `paths.dotnet` relative to itself.
+    /// Files that render to nothing but whitespace are left out.
+    pub fn render(&self, plan: &mut Plan, paths: &ModulePaths, context: &Value) -> Result<()> {
+        for (name, content) in &self.files {
+            if let Some((path, content)) = render_file(name, content, paths, context)? {
+                plan.add_file(&path, content);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the pack file `name`, e.g. `root/LICENSE`, renders to a file that is planned.
+    pub fn renders(&self, name: &str, paths: &ModulePaths, context: &Value) -> Result<bool> {
+        match self.files.get(name) {
+            Some(content) => Ok(render_file(name, content, paths, context)?.is_some()),
+            None => Ok(false),
+        }
+    }
+}
+
+/// The path and content of the pack file `name` rendered into the layout folder of its
+/// part, or `None` when it renders to nothing but whitespace.
+fn render_file(name: &str, content: &str, paths: &ModulePaths, context: &Value)
+    -> Result<Option<(PathBuf, String)>> {
+    let (part, path) = name.split_once('/').unwrap_or_default();
+    let dir = match part {
+        "upm" => paths.upm.as_path(),
+        "unity" => paths.unity.as_path(),
+        "dotnet" => paths.dotnet.as_path(),
+        _ => Path::new(""),
+    };
+    let path = dir.join(render(name, path, context)?);
+    let file_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut context = context.clone();
+    if let Value::Object(map) = &mut context {
+        map.insert(String::from("paths"), json!({
+            "root": link(file_dir, Path::new("")),
+            "upm": link(file_dir, &paths.upm),
+            "unity": link(file_dir, &paths.unity),
+            "dotnet": link(file_dir, &paths.dotnet),
+        }));
+    }
+    let content = render(name, content, &context)?;
+    Ok((!content.trim().is_empty()).then_some((path, content)))
+}
+
+/// Renders `{{var}}`, `{{var | filter}}`, `{{#if var}}...{{else}}...{{/if}}`,
+/// `{{#unless var}}...{{/unless}}` and `{{#each list}}...{{/each}}`. Inside a loop the
+/// fields of the item are in scope, along with `this`, `@index`, `@first` and `@last`.
+/// Block tags alone on their line leave no blank line behind.
+pub fn render(name: &str, template: &str, context: &Value) -> Result<String> {
+    let tokens = tokenize(name, template)?;
+    let mut pos = 0;
+    let (nodes, end) = parse(name, &tokens, &mut pos)?;
+    if let Some((tag, line)) = end {
+        return Err(Error::Template(format!("{}:{} - Unexpected {{{{{}}}}}", name, line, tag)));
+    }
+    let mut scopes = vec![Scope { value: context.clone(), index: None }];
+    let mut output = String::new();
+    render_nodes(name, &nodes, &mut scopes, &mut output)?;
+    Ok(output)
+}
+
+fn find_pack(template: &str) -> Result<std::path::PathBuf> {
+    let path = Path::new(template);
+    if path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    user_config_dir()
+        .map(|dir| dir.join("templates").join(template))
+        .filter(|dir| dir.is_dir())
+        .ok_or_else(|| Error::Template(format!("Template '{}' was not found", template)))
+}
+
+fn read_pack(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    let entries = fs::read_dir(dir).map_err(|e| Error::Io(format!("{} - {}", dir.display(), e)))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_pack(root, &path, files)?;
+            continue;
+        }
+        let name = path.strip_prefix(root).unwrap_or(&path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let part = name.split('/').next().unwrap_or_default();
+        if !name.contains('/') || !PARTS.contains(&part) {
+            return Err(Error::Template(format!(
+                "{} - Template files must be in one of the {} folders", path.display(), PARTS.join(", "))));
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| Error::Template(format!("{} - {}", path.display(), e)))?;
+        files.insert(name, content);
+    }
+    Ok(())
+}
+
+/// Splits the template into text and `{{...}}` tags, each tag with its line. A block tag
+/// alone on its line is taken out with the indentation before it and the line break after it.
+fn tokenize(name: &str, template: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    let mut line = 1;
+    let mut at_line_start = true;
+    while let Some(start) = rest.find("{{") {
+        let mut text = &rest[..start];
+        line += text.matches('\n').count();
+        let after_open = &rest[start + 2..];
+        let end = after_open.find("}}").ok_or_else(|| Error::Template(
+            format!("{}:{} - Unclosed {{{{", name, line)))?;
+        let tag = after_open[..end].trim();
+        let mut after = &after_open[end + 2..];
+
+        let is_block = tag.starts_with('#') || tag.starts_with('/') || tag == "else";
+        let line_prefix = match text.rfind('\n') {
+            Some(idx) => Some(&text[idx + 1..]),
+            None if at_line_start => Some(text),
+            None => None,
+        };
+        let line_rest = after.find('\n').map_or(after, |idx| &after[..idx]);
+        at_line_start = false;
+        if let Some(prefix) = line_prefix.filter(|prefix| prefix.trim().is_empty())
+            && is_block
+            && line_rest.trim().is_empty() {
+            text = &text[..text.len() - prefix.len()];
+            after = after.find('\n').map_or("", |idx| &after[idx + 1..]);
+            at_line_start = true;
+        }
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(text.to_string()));
+        }
+        tokens.push(Token::Tag(tag.to_string(), line));
+        line += after_open[..after_open.len() - after.len()].matches('\n').count();
+        rest = after;
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+    Ok(tokens)
+}
+
+/// Parses nodes up to the end of the template or to an `else` or closing tag, which is
+/// returned with its line.
+fn parse(name: &str, tokens: &[Token], pos: &mut usize) -> Result<Block> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        let (tag, line) = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text.clone()));
+                continue;
+            },
+            Token::Tag(tag, line) => (tag.as_str(), *line),
+        };
+
+        if tag == "else" || tag.starts_with('/') {
+            return Ok((nodes, Some((tag.to_string(), line))));
+        }
+
+        if let Some(block) = tag.strip_prefix('#') {
+            let (keyword, path) = block.split_once(char::is_whitespace).unwrap_or((block, ""));
+            let path = path.trim().to_string();
+            if path.is_empty() {
+                return Err(Error::Template(format!("{}:{} - {{{{#{}}}}} needs a value", name, line, keyword)));
+            }
+            let closing = format!("/{}", keyword);
+            let (body, end) = parse(name, tokens, pos)?;
+            let (otherwise, end) = match end {
+                Some((tag, _)) if tag == "else" && keyword != "each" => parse(name, tokens, pos)?,
+                end => (Vec::new(), end),
+            };
+            if end.as_ref().is_none_or(|(tag, _)| *tag != closing) {
+                return Err(Error::Template(format!("{}:{} - {{{{#{}}}}} is not closed by {{{{{}}}}}",
+                                                   name, line, keyword, closing)));
+            }
+            nodes.push(match keyword {
+                "if" | "unless" => Node::If { path, negate: keyword == "unless", line, then: body, otherwise },
+                "each" => Node::Each { path, line, body },
+                _ => return Err(Error::Template(format!("{}:{} - Unknown block {{{{#{}}}}}", name, line, keyword))),
+            });
+            continue;
+        }
+
+        let mut parts = tag.split('|').map(str::trim);
+        let path = parts.next().unwrap_or_default().to_string();
+        let filters = parts.map(str::to_string).collect();
+        nodes.push(Node::Var { path, filters, line });
+    }
+    Ok((nodes, None))
+}
+
+fn render_nodes(name: &str, nodes: &[Node], scopes: &mut Vec<Scope>, output: &mut String) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Var { path, filters, line } => {
+                let value = lookup(name, path, *line, scopes)?;
+                let mut text = match value {
+                    Value::Null => String::new(),
+                    Value::String(text) => text,
+                    Value::Bool(_) | Value::Number(_) => value.to_string(),
+                    _ => return Err(Error::Template(format!(
+                        "{}:{} - '{}' is a list or an object and can not be printed", name, line, path))),
+                };
+                for filter in filters {
+                    text = match filter.as_str() {
+                        "lower" => text.to_lowercase(),
+                        "upper" => text.to_uppercase(),
+                        "backslash" => text.replace('/', "\\"),
+                        _ => return Err(Error::Template(format!("{}:{} - Unknown filter '{}'", name, line, filter))),
+                    };
+                }
+                output.push_str(&text);
+            },
+            Node::If { path, negate, line, then, otherwise } => {
+                let value = lookup(name, path, *line, scopes)?;
+                let branch = if is_truthy(&value) != *negate { then } else { otherwise };
+                render_nodes(name, branch, scopes, output)?;
+            },
+            Node::Each { path, line, body } => {
+                let items = match lookup(name, path, *line, scopes)? {
+                    Value::Array(items) => items,
+                    Value::Null => Vec::new(),
+                    _ => return Err(Error::Template(format!("{}:{} - '{}' is not a list", name, line, path))),
+                };
+                let count = items.len();
+                for (idx, item) in items.into_iter().enumerate() {
+                    scopes.push(Scope { value: item, index: Some((idx, count)) });
+                    let result = render_nodes(name, body, scopes, output);
+                    scopes.pop();
+                    result?;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Finds the first segment of a dotted path in the innermost scope that has it.
+fn lookup(name: &str, path: &str, line: usize, scopes: &[Scope]) -> Result<Value> {
+    let unknown = || Error::Template(format!("{}:{} - Unknown placeholder '{}'", name, line, path));
+    let scope = scopes.last().ok_or_else(unknown)?;
+    match (path, scope.index) {
+        ("this", _) => return Ok(scope.value.clone()),
+        ("@index", Some((idx, _))) => return Ok(json!(idx)),
+        ("@first", Some((idx, _))) => return Ok(json!(idx == 0)),
+        ("@last", Some((idx, count))) => return Ok(json!(idx + 1 == count)),
+        _ => {},
+    }
+
+    let mut segments = path.split('.');
+    let first = segments.next().unwrap_or_default();
+    let mut value = scopes.iter().rev()
+        .find_map(|scope| scope.value.get(first))
+        .ok_or_else(unknown)?;
+    for segment in segments {
+        value = value.get(segment).ok_or_else(unknown)?;
+    }
+    Ok(value.clone())
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Value {
+        json!({
+            "name": "com.e314.foo",
+            "display_name": "E314.Foo",
+            "tests": { "editor": true, "runtime": false },
+            "dependencies": [
+                { "name": "com.e314.exceptions", "version": "1.1.2" },
+                { "name": "com.e314.protect", "version": "2.1.1" },
+            ],
+        })
+    }
+
+    fn template_error(template: &str) -> String {
+        match render("test.md", template, &context()) {
+            Err(Error::Template(message)) => message,
+            other => panic!("expected a template error, got {:?}", other.map_err(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn renders_variables_and_filters() {
+        let output = render("test.md", "{{display_name}} {{ name | upper }} {{display_name | lower}}", &context());
+        assert_eq!(output.unwrap(), "E314.Foo COM.E314.FOO e314.foo");
+    }
+
+    #[test]
+    fn renders_conditions_and_loops() {
+        let template = "{{#if tests.editor}}editor{{else}}none{{/if}} \
+                        {{#unless tests.runtime}}no runtime{{/unless}} \
+                        {{#each dependencies}}{{@index}}:{{name}}@{{version}}{{#unless @last}}, {{/unless}}{{/each}}";
+        assert_eq!(render("test.md", template, &context()).unwrap(),
+                   "editor no runtime 0:com.e314.exceptions@1.1.2, 1:com.e314.protect@2.1.1");
+    }
+
+    #[test]
+    fn block_tags_alone_on_their_line_leave_no_blank_line() {
+        let template = "# Foo\n{{#if tests.editor}}\n  {{#each dependencies}}\n- {{name}}\n  {{/each}}\n{{/if}}\nEnd\n";
+        assert_eq!(render("test.md", template, &context()).unwrap(),
+                   "# Foo\n- com.e314.exceptions\n- com.e314.protect\nEnd\n");
+    }
+
+    #[test]
+    fn block_tags_inside_text_keep_the_line() {
+        let template = "Tests: {{#if tests.editor}}editor{{/if}}\nEnd";
+        assert_eq!(render("test.md", template, &context()).unwrap(), "Tests: editor\nEnd");
+    }
+
+    #[test]
+    fn reports_unclosed_tags_and_blocks_with_their_line() {
+        assert_eq!(template_error("Line\n{{name"), "test.md:2 - Unclosed {{");
+        assert_eq!(template_error("{{#if tests.editor}}\nText\n"),
+                   "test.md:1 - {{#if}} is not closed by {{/if}}");
+        assert_eq!(template_error("{{#each dependencies}}{{/if}}"),
+                   "test.md:1 - {{#each}} is not closed by {{/each}}");
+        assert_eq!(template_error("Text\n{{/if}}"), "test.md:2 - Unexpected {{/if}}");
+        assert_eq!(template_error("{{#if}}{{/if}}"), "test.md:1 - {{#if}} needs a value");
+        assert_eq!(template_error("{{#with name}}{{/with}}"), "test.md:1 - Unknown block {{#with}}");
+    }
+
+    #[test]
+    fn reports_unknown_placeholders_and_filters() {
+        assert_eq!(template_error("\n\n{{version}}"), "test.md:3 - Unknown placeholder 'version'");
+        assert_eq!(template_error("{{tests.play_mode}}"), "test.md:1 - Unknown placeholder 'tests.play_mode'");
+        assert_eq!(template_error("{{name | title}}"), "test.md:1 - Unknown filter 'title'");
+        assert_eq!(template_error("{{dependencies}}"),
+                   "test.md:1 - 'dependencies' is a list or an object and can not be printed");
+    }
+}
\ No newline at end of file
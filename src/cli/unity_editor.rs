@@ -0,0 +1,226 @@
+﻿use crate::cli::config::home_dir;
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Editor of the project when none is configured or installed.
+const DEFAULT_VERSION: &str = "6000.1.4f1";
+const DEFAULT_REVISION: &str = "03270eb687c6";
+/// Bytes of an editor executable read at a time while looking for its revision.
+const SCAN_CHUNK: usize = 4 << 20;
+
+/// A Unity editor version, e.g. `6000.1.4f1`, with the revision of its build when known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnityVersion {
+    pub version: String,
+    pub revision: Option<String>,
+}
+
+impl UnityVersion {
+    /// `version`, or the newest editor installed with Unity Hub, or the built-in default.
+    /// The revision is read from the installed editor of the version.
+    pub fn select(version: Option<&str>) -> Self {
+        let installed = installed_editors();
+        let (version, install) = match version {
+            Some(version) => match installed.into_iter().find(|(installed, _)| installed == version) {
+                Some((version, install)) => (version, Some(install)),
+                None => (version.to_string(), None),
+            },
+            None => match installed.into_iter().next() {
+                Some((version, install)) => (version, Some(install)),
+                None => (DEFAULT_VERSION.to_string(), None),
+            },
+        };
+        let revision = install.and_then(|install| read_revision(&install, &version))
+            .or_else(|| (version == DEFAULT_VERSION).then(|| DEFAULT_REVISION.to_string()));
+        UnityVersion { version, revision }
+    }
+
+    /// The `major.minor` release line, the `unity` field of a `package.json`.
+    pub fn release_line(&self) -> String {
+        self.version.split('.').take(2).collect::<Vec<_>>().join(".")
+    }
+}
+
+/// A version such as `2022.3.10f1` or `6000.1.0b2`.
+pub fn is_valid_unity_version(version: &str) -> bool {
+    let version_regex = regex::Regex::new(r"^\d+\.\d+\.\d+[abcfpx]\d+$").unwrap();
+    version_regex.is_match(version)
+}
+
+/// Splits the oldest Unity version a package supports, a release line such as `2022.3` or
+/// a release such as `2022.3.10f1`, into the `unity` and `unityRelease` of its `package.json`.
+pub fn split_min_version(version: &str) -> Option<(String, Option<String>)> {
+    let line_regex = regex::Regex::new(r"^\d{4}\.\d+$").unwrap();
+    if line_regex.is_match(version) {
+        return Some((version.to_string(), None));
+    }
+    if !is_valid_unity_version(version) {
+        return None;
+    }
+    let (line, release) = version.rsplit_once('.')?;
+    Some((line.to_string(), Some(release.to_string())))
+}
+
+/// Versions and install folders of the editors in the Unity Hub install folders, newest first.
+fn installed_editors() -> Vec<(String, PathBuf)> {
+    let mut editors: Vec<(String, PathBuf)> = hub_editor_dirs().iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| {
+            let version = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+            let has_editor = path.join("Editor").is_dir() || path.join("Unity.app").is_dir();
+            (is_valid_unity_version(&version) && has_editor).then_some((version, path))
+        })
+        .collect();
+    editors.sort_by(|a, b| compare_versions(&b.0, &a.0));
+    editors.dedup_by(|a, b| a.0 == b.0);
+    editors
+}
+
+/// The folder set in Unity Hub for new installs, then the default one of the platform.
+fn hub_editor_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let secondary_path = hub_config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join("secondaryInstallPath.json")).ok())
+        .and_then(|content| serde_json::from_str::<String>(&content).ok())
+        .filter(|path| !path.is_empty());
+    if let Some(path) = secondary_path {
+        dirs.push(PathBuf::from(path));
+    }
+    if cfg!(target_os = "windows") {
+        dirs.push(PathBuf::from(r"C:\Program Files\Unity\Hub\Editor"));
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/Applications/Unity/Hub/Editor"));
+    } else if let Some(home) = home_dir() {
+        dirs.push(home.join("Unity").join("Hub").join("Editor"));
+    }
+    dirs
+}
+
+fn hub_config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("UnityHub"))
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support").join("UnityHub"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".config")))
+            .map(|dir| dir.join("UnityHub"))
+    }
+}
+
+/// The revision of the editor `version` installed in `install`: the `UnityBuildNumber` of
+/// the `Info.plist` of a macOS install, or the revision the executable of a Linux or Windows
+/// install carries after its version, e.g. `6000.1.4f1_03270eb687c6`.
+fn read_revision(install: &Path, version: &str) -> Option<String> {
+    let plist = install.join("Unity.app").join("Contents").join("Info.plist");
+    if let Ok(plist) = fs::read_to_string(plist) {
+        let (_, rest) = plist.split_once("<key>UnityBuildNumber</key>")?;
+        let (_, rest) = rest.split_once("<string>")?;
+        let (revision, _) = rest.split_once("</string>")?;
+        return Some(revision.trim().to_string()).filter(|revision| !revision.is_empty());
+    }
+    ["Unity", "Unity.exe"].iter()
+        .map(|name| install.join("Editor").join(name))
+        .find(|executable| executable.is_file())
+        .and_then(|executable| scan_revision(&executable, version))
+}
+
+/// Looks through the file for `<version>_` followed by a 12 digit hexadecimal revision, as
+/// ASCII or as the UTF-16 of the Windows version resource, a chunk at a time.
+fn scan_revision(path: &Path, version: &str) -> Option<String> {
+    let prefix = format!("{}_", version);
+    let ascii = prefix.as_bytes().to_vec();
+    let wide: Vec<u8> = prefix.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    // Kept from the end of a chunk so a match split between two chunks is found in the next.
+    let overlap = wide.len() + 26;
+
+    let mut file = File::open(path).ok()?;
+    let mut bytes = Vec::new();
+    loop {
+        let start = bytes.len();
+        bytes.resize(start + SCAN_CHUNK, 0);
+        let read = file.read(&mut bytes[start..]).ok()?;
+        bytes.truncate(start + read);
+        let revision = find_revision(&bytes, &ascii, 1).or_else(|| find_revision(&bytes, &wide, 2));
+        if revision.is_some() || read == 0 {
+            return revision;
+        }
+        bytes.drain(..bytes.len().saturating_sub(overlap));
+    }
+}
+
+/// The revision after the first `prefix` in `bytes` whose characters are `width` bytes wide.
+fn find_revision(bytes: &[u8], prefix: &[u8], width: usize) -> Option<String> {
+    let is_hex = |unit: &[u8]| matches!(unit[0], b'0'..=b'9' | b'a'..=b'f')
+        && unit[1..].iter().all(|byte| *byte == 0);
+    bytes.windows(prefix.len())
+        .enumerate()
+        .filter(|(_, window)| *window == prefix)
+        .find_map(|(at, _)| {
+            let start = at + prefix.len();
+            let units: Vec<&[u8]> = bytes.get(start..start + 12 * width)?.chunks(width).collect();
+            let ends = bytes.get(start + 12 * width..start + 13 * width).is_none_or(|next| !is_hex(next));
+            (units.iter().all(|unit| is_hex(unit)) && ends)
+                .then(|| units.iter().map(|unit| unit[0] as char).collect())
+        })
+}
+
+/// Orders valid versions by their numbers and then by the release type: alpha, beta,
+/// china, final, patch, experimental.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let key = |version: &str| -> (Vec<u64>, usize, u64) {
+        let split = version.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(version.len());
+        let (numbers, build) = version.split_at(split);
+        let numbers = numbers.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+        let kind = build.chars().next().and_then(|kind| "abcfpx".find(kind)).unwrap_or(0);
+        (numbers, kind, build.get(1..).and_then(|number| number.parse().ok()).unwrap_or(0))
+    };
+    key(a).cmp(&key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::test_fixtures::TempDir;
+
+    #[test]
+    fn finds_the_revision_after_the_version() {
+        let dir = TempDir::new("editor-ascii");
+        let path = dir.write("Unity", b"\x7fELF\0\x016000.1.4f\x006000.1.4f1_03270eb687c6\0Unity");
+        assert_eq!(scan_revision(&path, "6000.1.4f1").as_deref(), Some("03270eb687c6"));
+        assert_eq!(scan_revision(&path, "2022.3.10f1"), None);
+    }
+
+    #[test]
+    fn finds_the_revision_of_a_windows_version_resource() {
+        let text: Vec<u8> = "ProductVersion\u{0}2022.3.10f1_ff3792e53c62\0".encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let dir = TempDir::new("editor-wide");
+        let path = dir.write("Unity", [b"MZ\x90\0".as_slice(), &text].concat());
+        assert_eq!(scan_revision(&path, "2022.3.10f1").as_deref(), Some("ff3792e53c62"));
+    }
+
+    #[test]
+    fn skips_versions_without_a_full_revision() {
+        let dir = TempDir::new("editor-partial");
+        let path = dir.write("Unity", b"6000.1.4f1_0327 6000.1.4f1_03270eb687c6aa 6000.1.4f1_6eacc8284459.");
+        assert_eq!(scan_revision(&path, "6000.1.4f1").as_deref(), Some("6eacc8284459"));
+    }
+
+    #[test]
+    fn finds_a_revision_split_between_chunks() {
+        let mut content = vec![b' '; SCAN_CHUNK - 15];
+        content.extend_from_slice(b"6000.1.4f1_03270eb687c6\n");
+        let dir = TempDir::new("editor-chunks");
+        let path = dir.write("Unity", content);
+        assert_eq!(scan_revision(&path, "6000.1.4f1").as_deref(), Some("03270eb687c6"));
+    }
+}
\ No newline at end of file
@@ -1,142 +1,242 @@
-﻿use std::collections::HashMap;
-use std::fs;
-use std::path::{Path, PathBuf};
-use chrono::{Datelike, Utc};
-use serde_json::{json, to_string_pretty};
+﻿use crate::cli::error::{Error, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::cli::meta::{self, GuidMode};
+use crate::cli::plan::{to_json, Plan};
 use crate::cli::repositories::RepoInfo;
 
+/// How an assembly definition references other assemblies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceMode {
+    /// By assembly name, e.g. `E314.Exceptions`.
+    #[default]
+    Name,
+    /// By the GUID of the `.asmdef`, e.g. `GUID:1b2c...`, which survives renaming. Falls
+    /// back to the name for dependencies whose GUID is not in the registry.
+    Guid,
+}
+
+pub const REFERENCE_MODES: [&str; 2] = ["name", "guid"];
+
+/// GUIDs of the test runner assemblies of the Unity Test Framework.
+const ENGINE_TEST_RUNNER_GUID: &str = "27619889b8ba8c24980f49ee34dbb44a";
+const EDITOR_TEST_RUNNER_GUID: &str = "0acc523941302664db1f4e527237feb3";
+
+/// Which test assemblies the package gets: edit mode tests in `Tests/Editor` and play
+/// mode tests in `Tests/Runtime`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestAssemblies {
+    Editor,
+    Runtime,
+    #[default]
+    Both,
+    None,
+}
+
+pub const TEST_ASSEMBLIES: [&str; 4] = ["editor", "runtime", "both", "none"];
+
+impl TestAssemblies {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "editor" => Some(TestAssemblies::Editor),
+            "runtime" => Some(TestAssemblies::Runtime),
+            "both" => Some(TestAssemblies::Both),
+            "none" => Some(TestAssemblies::None),
+            _ => None,
+        }
+    }
+
+    pub fn editor(&self) -> bool {
+        matches!(self, TestAssemblies::Editor | TestAssemblies::Both)
+    }
+
+    pub fn runtime(&self) -> bool {
+        matches!(self, TestAssemblies::Runtime | TestAssemblies::Both)
+    }
+}
+
+impl ReferenceMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "name" => Some(ReferenceMode::Name),
+            "guid" => Some(ReferenceMode::Guid),
+            _ => None,
+        }
+    }
+}
+
+/// A sample of the package that can be imported from the Package Manager window.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sample {
+    pub display_name: String,
+    pub description: String,
+    /// Folder of the sample in the package, e.g. `Samples~/Basic`.
+    pub path: String,
+}
+
+impl Sample {
+    /// The sample in the `Samples~` folder `folder`, named after it.
+    pub fn in_folder(folder: &str) -> Self {
+        Sample {
+            display_name: folder.to_string(),
+            description: String::new(),
+            path: format!("Samples~/{}", folder),
+        }
+    }
+}
+
+/// The fields of `package.json` besides the name, version, description and author that
+/// the Package Manager and OpenUPM show. Empty ones are left out of the file.
+#[derive(Debug, Default, Clone)]
+pub struct PackageDetails {
+    /// Oldest Unity release line the package supports, e.g. `2022.3`.
+    pub unity: String,
+    /// Oldest release of that line, e.g. `10f1`.
+    pub unity_release: Option<String>,
+    pub keywords: Vec<String>,
+    /// SPDX license expression, e.g. `MIT`.
+    pub license: String,
+    /// Git URL of the repository of the module.
+    pub repository: Option<String>,
+    pub documentation_url: Option<String>,
+    pub changelog_url: Option<String>,
+    pub licenses_url: Option<String>,
+    pub samples: Vec<Sample>,
+    pub author_url: Option<String>,
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct UPM {
     name: String,
     display_name: String,
     version: String,
     description: String,
+    details: PackageDetails,
     dependencies: Vec<RepoInfo>,
     author_name: String,
     author_email: String,
+    references: ReferenceMode,
+    guids: GuidMode,
+    tests: TestAssemblies,
 }
 
 impl UPM {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         display_name: String,
         version: String,
         description: String,
+        details: PackageDetails,
         author_name: String,
         author_email: String,
-        dependencies: Vec<RepoInfo>) -> Self {
+        dependencies: Vec<RepoInfo>,
+        references: ReferenceMode,
+        guids: GuidMode,
+        tests: TestAssemblies) -> Self {
 
         UPM {
             name,
             display_name,
             version,
             description,
+            details,
             author_name,
             author_email,
             dependencies,
+            references,
+            guids,
+            tests,
         }
     }
 
-    pub fn create(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let path = Path::new(self.name.as_str());
-        let upm_path = path.join("UPM".to_string());
-        fs::create_dir_all(&upm_path)?;
-        self.create_package(&upm_path)?;
-        self.create_license(&upm_path)?;
-        self.create_changelog(&upm_path)?;
-        self.create_readme(&upm_path)?;
-        self.create_runtime(&upm_path)?;
-        self.create_editor(&upm_path)?;
-        self.create_tests(&upm_path)?;
-        self.create_documentation(&upm_path)?;
+    pub fn create(&self, plan: &mut Plan, upm_path: &Path) -> Result<()> {
+        plan.add_dir(upm_path);
+        self.create_package(plan, upm_path)?;
+        let runtime_reference = self.create_runtime(plan, upm_path)?;
+        let editor_reference = self.create_editor(plan, upm_path, &runtime_reference)?;
+        self.create_tests(plan, upm_path, &runtime_reference, &editor_reference)?;
         Ok(())
     }
 
-    fn create_package(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let mut dependencies: HashMap<String, String> = HashMap::new();
-        self.dependencies.iter().for_each(|dep| {
-            dependencies.insert(dep.name.clone(), dep.version.clone());
-        });
+    fn reference(&self, name: String, guid: Option<&String>) -> String {
+        match (self.references, guid) {
+            (ReferenceMode::Guid, Some(guid)) => format!("GUID:{}", guid),
+            _ => name,
+        }
+    }
 
-        let package_json = json!({
+    /// References to the runtime assemblies of the dependencies, and to their editor
+    /// assemblies for `editor`.
+    fn dependency_references(&self, editor: bool) -> Vec<String> {
+        let mut references = Vec::new();
+        for dep in &self.dependencies {
+            references.push(self.reference(dep.runtime_assembly(), dep.assemblies.runtime_guid.as_ref()));
+            if editor && let Some(name) = &dep.assemblies.editor {
+                references.push(self.reference(name.clone(), dep.assemblies.editor_guid.as_ref()));
+            }
+        }
+        references
+    }
+
+    fn create_package(&self, plan: &mut Plan, path: &Path) -> Result<()> {
+        let dependencies: BTreeMap<&str, &str> = self.dependencies.iter()
+            .map(|dep| (dep.name.as_str(), dep.version.as_str()))
+            .collect();
+
+        let details = &self.details;
+        let samples: Vec<Value> = details.samples.iter()
+            .map(|sample| json!({
+                "displayName": sample.display_name,
+                "description": sample.description,
+                "path": sample.path,
+            }))
+            .collect();
+        let repository = details.repository.as_ref()
+            .map(|url| json!({ "type": "git", "url": url }));
+
+        let package_json = without_empty(json!({
             "name": self.name,
             "displayName": self.display_name,
             "version": self.version,
-            "author": {
+            "author": without_empty(json!({
                 "name": self.author_name,
-                "email": self.author_email
-            },
+                "email": self.author_email,
+                "url": details.author_url
+            })),
             "description": self.description,
-            "dependencies": dependencies
-        });
+            "unity": details.unity,
+            "unityRelease": details.unity_release,
+            "keywords": details.keywords,
+            "license": details.license,
+            "repository": repository,
+            "documentationUrl": details.documentation_url,
+            "changelogUrl": details.changelog_url,
+            "licensesUrl": details.licenses_url,
+            "dependencies": dependencies,
+            "samples": samples
+        }));
 
-        let package_content = to_string_pretty(&package_json)?;
-        fs::write(path.join("package.json"), package_content)?;
-        Ok(())
-    }
-
-    fn create_license(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let license_content = format!(
-            "MIT License\n\n\
-            Copyright (c) {} {} ({})\n\n\
-            Permission is hereby granted, free of charge, to any person obtaining a copy\n\
-            of this software and associated documentation files (the \"Software\"), to deal\n\
-            in the Software without restriction, including without limitation the rights\n\
-            to use, copy, modify, merge, publish, distribute, sublicense, and/or sell\n\
-            copies of the Software, and to permit persons to whom the Software is\n\
-            furnished to do so, subject to the following conditions:\n\n\
-            The above copyright notice and this permission notice shall be included in all\n\
-            copies or substantial portions of the Software.\n\n\
-            THE SOFTWARE IS PROVIDED \"AS IS\", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR\n\
-            IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,\n\
-            FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE\n\
-            AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER\n\
-            LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,\n\
-            OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE\n\
-            SOFTWARE.\n",
-            Utc::now().year(), self.author_name, self.author_email
-        );
-        fs::write(path.join("LICENSE.md"), license_content)?;
-        Ok(())
-    }
-    
-    fn create_changelog(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let changelog_content = format!(
-            "# Changelog\n\n\
-            All notable changes to this project will be documented in this file.\n\n\
-            The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),\n\
-            and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n\n\
-            ## [{}]\n\n\
-            ### Added\n\n\
-            - Initial release\n",
-            self.version
-        );
-        fs::write(path.join("CHANGELOG.md"), changelog_content)?;
-        Ok(())
-    }
-    
-    fn create_readme(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let readme_content = format!(
-            "# {}\n\n\
-            {}\n\n\
-            ## Installation\n\n\
-            - [Installation - RU](Documentation~/installation-ru.md)\n\
-            - [Installation - EN](Documentation~/installation-en.md)\n\n\
-            ## Instructions\n\n\
-            - [Instructions - RU](Documentation~/instructions-ru.md)\n\
-            - [Instructions - EN](Documentation~/instructions-en.md)\n",
-            self.display_name, self.description
-        );
-        fs::write(path.join("README.md"), readme_content)?;
+        validate_package(&package_json)?;
+        let package_content = to_json(&package_json)?;
+        plan.add_file(path.join("package.json"), package_content);
+        for sample in &details.samples {
+            plan.add_dir(path.join(&sample.path));
+        }
         Ok(())
     }
 
-    fn create_runtime(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    /// Returns the reference other assemblies of the package use for the runtime assembly.
+    fn create_runtime(&self, plan: &mut Plan, path: &Path) -> Result<String> {
         let runtime_path = path.join("Runtime");
-        fs::create_dir_all(&runtime_path)?;
+        plan.add_dir(&runtime_path);
 
-        let references: Vec<String> = self.dependencies.iter()
-            .map(|dep| dep.name.clone())
-            .collect();
+        let references = self.dependency_references(false);
 
         let asmdef_content = json!({
             "name": self.display_name,
@@ -153,21 +253,33 @@ impl UPM {
             "noEngineReferences": false
         });
 
-        let asmdef_content = to_string_pretty(&asmdef_content)?;
-        fs::write(runtime_path.join(format!("{}.asmdef", self.display_name)), asmdef_content)?;
-        Ok(())
+        let asmdef_content = to_json(&asmdef_content)?;
+        let asmdef_path = runtime_path.join(format!("{}.asmdef", self.display_name));
+        plan.add_file(&asmdef_path, asmdef_content);
+        Ok(self.own_reference(plan, path, &asmdef_path, &self.display_name))
     }
 
-    fn create_editor(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    /// Reference to an assembly of the package; its `.meta` is planned now for a GUID.
+    fn own_reference(&self, plan: &mut Plan, path: &Path, asmdef_path: &Path, name: &str) -> String {
+        let guid = match self.references {
+            ReferenceMode::Guid => Some(meta::plan_meta(plan, path, &self.name, self.guids, asmdef_path, false)),
+            ReferenceMode::Name => None,
+        };
+        self.reference(name.to_string(), guid.as_ref())
+    }
+
+    /// Returns the reference other assemblies of the package use for the editor assembly.
+    fn create_editor(&self, plan: &mut Plan, path: &Path, runtime_reference: &str) -> Result<String> {
         let runtime_path = path.join("Editor");
-        fs::create_dir_all(&runtime_path)?;
+        plan.add_dir(&runtime_path);
+
+        let mut references = vec![runtime_reference.to_string()];
+        references.extend(self.dependency_references(true));
 
         let asmdef_content = json!({
             "name": format!("{}.Editor", self.display_name),
             "rootNamespace": format!("{}.Editor", self.display_name),
-            "references": [
-                self.display_name
-            ],
+            "references": references,
             "includePlatforms": [
                 "Editor"
             ],
@@ -181,193 +293,120 @@ impl UPM {
             "noEngineReferences": false
         });
 
-        let asmdef_content = to_string_pretty(&asmdef_content)?;
-        fs::write(runtime_path.join(format!("{}Editor.asmdef", self.display_name)), asmdef_content)?;
-        Ok(())
+        let asmdef_content = to_json(&asmdef_content)?;
+        let asmdef_path = runtime_path.join(format!("{}Editor.asmdef", self.display_name));
+        plan.add_file(&asmdef_path, asmdef_content);
+        Ok(self.own_reference(plan, path, &asmdef_path, &format!("{}.Editor", self.display_name)))
     }
 
-    fn create_tests(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    fn create_tests(&self, plan: &mut Plan, path: &Path, runtime_reference: &str, editor_reference: &str) -> Result<()> {
         let tests_path = path.join("Tests");
-        fs::create_dir_all(&tests_path)?;
+        if self.tests.editor() {
+            let mut references = vec![runtime_reference.to_string(), editor_reference.to_string()];
+            references.extend(self.dependency_references(true));
+            let asmdef_content = self.test_asmdef(&format!("{}.Editor.Tests", self.display_name), references, true)?;
+            let editor_tests_path = tests_path.join("Editor");
+            plan.add_dir(&editor_tests_path);
+            plan.add_file(editor_tests_path.join(format!("{}.Editor.Tests.asmdef", self.display_name)), asmdef_content);
+        }
+        if self.tests.runtime() {
+            let mut references = vec![runtime_reference.to_string()];
+            references.extend(self.dependency_references(false));
+            let asmdef_content = self.test_asmdef(&format!("{}.Tests", self.display_name), references, false)?;
+            let runtime_tests_path = tests_path.join("Runtime");
+            plan.add_dir(&runtime_tests_path);
+            plan.add_file(runtime_tests_path.join(format!("{}.Tests.asmdef", self.display_name)), asmdef_content);
+        }
+        Ok(())
+    }
+
+    /// A test assembly: compiled only with the test framework, referencing NUnit and the
+    /// test runner, and never referenced automatically by other assemblies.
+    fn test_asmdef(&self, name: &str, references: Vec<String>, editor: bool) -> Result<String> {
+        let mut test_references = vec![
+            self.reference(String::from("UnityEngine.TestRunner"), Some(&String::from(ENGINE_TEST_RUNNER_GUID))),
+            self.reference(String::from("UnityEditor.TestRunner"), Some(&String::from(EDITOR_TEST_RUNNER_GUID))),
+        ];
+        test_references.extend(references);
+        let include_platforms: Vec<&str> = if editor { vec!["Editor"] } else { Vec::new() };
 
         let asmdef_content = json!({
-            "name": format!("{}.Tests", self.display_name),
-            "rootNamespace": format!("{}.Tests", self.display_name),
-            "references": [
-                self.display_name
-            ],
-            "includePlatforms": [
-                "Editor"
-            ],
+            "name": name,
+            "rootNamespace": name,
+            "references": test_references,
+            "includePlatforms": include_platforms,
             "excludePlatforms": [],
             "allowUnsafeCode": false,
-            "overrideReferences": false,
-            "precompiledReferences": [],
-            "autoReferenced": true,
-            "defineConstraints": [],
+            "overrideReferences": true,
+            "precompiledReferences": [
+                "nunit.framework.dll"
+            ],
+            "autoReferenced": false,
+            "defineConstraints": [
+                "UNITY_INCLUDE_TESTS"
+            ],
             "versionDefines": [],
             "noEngineReferences": false
         });
-
-        let asmdef_content = to_string_pretty(&asmdef_content)?;
-        fs::write(tests_path.join(format!("{}.Tests.asmdef", self.display_name)), asmdef_content)?;
-        Ok(())
+        to_json(&asmdef_content)
     }
+}
 
-    fn create_documentation(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let doc_path = path.join("Documentation~");
-        fs::create_dir_all(&doc_path)?;
-        self.create_index(&doc_path)?;
-        self.create_installation_en(&doc_path)?;
-        self.create_installation_ru(&doc_path)?;
-        self.create_instructions_en(&doc_path)?;
-        self.create_instructions_ru(&doc_path)?;
-        Ok(())
-    }
-    
-    fn create_index(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let index = format!(
-            "# {}\n\n\
-            {}\n\n\
-            ## Installation\n\n\
-            - [Installation - RU](installation-ru.md)\n\
-            - [Installation - EN](installation-en.md)\n\n\
-            ## Instructions\n\n\
-            - [Instructions - RU](instructions-ru.md)\n\
-            - [Instructions - EN](instructions-en.md)\n",
-            self.display_name, self.description
-        );
-        fs::write(path.join("index.md"), index)?;
-        Ok(())
+/// `value` without the fields that are null or empty arrays.
+fn without_empty(mut value: Value) -> Value {
+    if let Value::Object(fields) = &mut value {
+        fields.retain(|_, field| !field.is_null() && field.as_array().is_none_or(|items| !items.is_empty()));
     }
+    value
+}
 
-    fn create_installation_en(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let index = format!(
-            "# Installation\n\n\
-            ## Content tree\n\n\
-            - [Installation](#installation)\n\
-              - [Content tree](#content-tree)\n\
-              - [Compatibility](#compatibility)\n\
-              - [Unity Package Manager. Git URL](#unity-package-manager-git-url)\n\
-              - [Unity Package Manager. OpenUPM](#unity-package-manager-openupm)\n\
-              - [NuGet](#nuget)\n\n\
-            ## Compatibility\n\n\
-            - The module has been tested with Unity 2022.3 LTS and above.\n\
-            - Compatible with .NET Standard 2.0 and above.\n\n\
-            ## Unity Package Manager. Git URL\n\n\
-            ```ps1\n\n\
-            https://github.com/\n\
-            ```\n\n\
-            1. Open Window → Package Manager.\n\n\
-            2. Click on + → Add package from git URL...\n\n\
-            3. Enter the URL and click Add.\n\n\
-            ### Unity Package Manager. OpenUPM\n\n\
-            ```ps1\n\n\
-            https://openupm.com/packages/{}.html\n\
-            ```\n\n\
-            1. Open Edit → Project Settings → Package Manager.\n\
-            2. Register a new OpenUPM registry if it hasn't been done yet.\n\
-            3. Add com.e314 to Scopes.\n\
-            4. Click Apply.\n\
-            5. Open Window → Package Manager.\n\
-            6. Click on the + button → Add package by name...\n\
-            7. Enter the Name `{}` and Version `{}`.\n\
-            8. Click Add.\n\n\
-            ## NuGet\n\n\
-            ```ps1\n\
-            https://www.nuget.org/packages/{}\n\
-            ```\n\n\
-            1. Open the command line.\n\
-            2. Navigate to the directory containing the project file.\n\
-            3. Run the command to install the NuGet package:\n\n\
-            ```sh\n\
-            dotnet add package {} -v {}\n\
-            ```\n",
-            self.name,
-            self.name,
-            self.version,
-            self.display_name,
-            self.display_name,
-            self.version
-        );
-        
-        fs::write(path.join("installation-en.md"), index)?;
-        Ok(())
-    }
+/// Checks `package` against the rules of the Unity package manifest schema, so that a
+/// package the Package Manager or OpenUPM would reject is not written.
+fn validate_package(package: &Value) -> Result<()> {
+    let matches = |pattern: &str, value: &str| regex::Regex::new(pattern).unwrap().is_match(value);
+    let is_url = |value: &str| matches(r"^https?://[^\s/]+\S*$", value);
 
-    fn create_installation_ru(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let index = format!(
-            "# Установка\n\n\
-            ## Содержание\n\n\
-            - [Установка](#установка)\n\
-              - [Содержание](#содержание)\n\
-              - [Совместимость](#совместимость)\n\
-              - [Unity Package Manager. Git URL](#unity-package-manager-git-url)\n\
-              - [Unity Package Manager. OpenUPM](#unity-package-manager-openupm)\n\
-              - [NuGet](#nuget)\n\n\
-            ## Совместимость\n\n\
-            - Модуль протестирован с Unity 2022.3 LTS и выше.\n\
-            - Совместим с .NET Standard 2.0 и выше.\n\n\
-            ## Unity Package Manager. Git URL\n\n\
-            ```ps1\n\n\
-            https://github.com/\n\
-            ```\n\n\
-            1. Открыть Window → Package Manager.\n\n\
-            2. Нажать на + → Add package from git URL...\n\n\
-            3. Ввести url и нажать Add.\n\n\
-            ### Unity Package Manager. OpenUPM\n\n\
-            ```ps1\n\n\
-            https://openupm.com/packages/{}.html\n\
-            ```\n\n\
-            1. Открыть Edit → Project Settings → Package Manager.\n\
-            2. Зарегистрировать новый реестр OpenUPM, если это еще не сделано.\n\
-            3. Добавьте `com.e314` в  Scopes.\n\
-            4. Нажать Apply.\n\
-            5. Открыть Window → Package Manager.\n\
-            6. Нажать на + button → Add package by name...\n\
-            7. Введите Имя `{}` и Версию `{}`.\n\
-            8. Нажмите Add.\n\n\
-            ## NuGet\n\n\
-            ```ps1\n\
-            https://www.nuget.org/packages/{}\n\
-            ```\n\n\
-            1. Открыть командную строку.\n\
-            2. Перейти в каталог, в котором находится файл проекта.\n\
-            3. Выполнить команду для установки пакета NuGet:\n\n\
-            ```sh\n\
-            dotnet add package {} -v {}\n\
-            ```\n",
-            self.name,
-            self.name,
-            self.version,
-            self.display_name,
-            self.display_name,
-            self.version
-        );
-
-        fs::write(path.join("installation-ru.md"), index)?;
-        Ok(())
-    }
+    let mut problems: Vec<(String, &str)> = Vec::new();
+    let mut check = |pointer: &str, problem: &'static str, rule: &dyn Fn(&str) -> bool| {
+        match package.pointer(pointer) {
+            Some(Value::String(value)) if !rule(value) => problems.push((pointer.to_string(), problem)),
+            Some(Value::String(_)) | None => {}
+            Some(_) => problems.push((pointer.to_string(), "not a string")),
+        }
+    };
 
-    fn create_instructions_en(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let index = format!(
-            "# {0}\n\n\
-            ## Description\n\n\
-            {1}\n",
-            self.display_name, self.description
-        );
-        fs::write(path.join("instructions-en.md"), index)?;
-        Ok(())
+    check("/name", "lowercase letters, digits, '.', '-' and '_' of at most 214 characters",
+          &|name| name.len() <= 214 && matches(r"^[a-z0-9][a-z0-9._-]*$", name));
+    check("/version", "not a semantic version",
+          &|version| matches(r"^\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?$", version));
+    check("/displayName", "empty", &|name| !name.trim().is_empty());
+    check("/description", "empty", &|description| !description.trim().is_empty());
+    check("/author/name", "empty", &|name| !name.trim().is_empty());
+    check("/author/email", "not an email address", &|email| matches(r"^[^@\s]+@[^@\s]+\.[^@\s]+$", email));
+    check("/author/url", "not an http(s) URL", &is_url);
+    check("/unity", "not a release line such as 2022.3", &|unity| matches(r"^\d{4}\.\d+$", unity));
+    check("/unityRelease", "not a release such as 10f1", &|release| matches(r"^\d+[abcfpx]\d+$", release));
+    check("/license", "not an SPDX license expression",
+          &|license| matches(r"^\(?[A-Za-z0-9.+-]+\)?( (AND|OR|WITH) \(?[A-Za-z0-9.+-]+\)?)*$", license));
+    check("/repository/type", "not git", &|kind| kind == "git");
+    check("/repository/url", "empty", &|url| !url.trim().is_empty());
+    check("/documentationUrl", "not an http(s) URL", &is_url);
+    check("/changelogUrl", "not an http(s) URL", &is_url);
+    check("/licensesUrl", "not an http(s) URL", &is_url);
+    let keywords = package["keywords"].as_array().map_or(0, Vec::len);
+    for index in 0..keywords {
+        check(&format!("/keywords/{}", index), "empty", &|keyword| !keyword.trim().is_empty());
+    }
+    let samples = package["samples"].as_array().map_or(0, Vec::len);
+    for index in 0..samples {
+        check(&format!("/samples/{}/displayName", index), "empty", &|name| !name.trim().is_empty());
+        check(&format!("/samples/{}/path", index), "not a folder in Samples~",
+              &|path| matches(r"^Samples~/[^/\\]+(/[^/\\]+)*$", path) && !path.split('/').any(|part| part == ".."));
     }
 
-    fn create_instructions_ru(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let index = format!(
-            "# {0}\n\n\
-            ## Описание\n\n\
-            {1}\n",
-            self.display_name, self.description
-        );
-        fs::write(path.join("instructions-en.md"), index)?;
-        Ok(())
+    match problems.into_iter().next() {
+        Some((pointer, problem)) => Err(Error::InvalidInput(format!(
+            "package.json: {} - {}", pointer.trim_start_matches('/').replace('/', "."), problem))),
+        None => Ok(()),
     }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+﻿use crate::cli::error::Result;
+use crate::cli::git;
+use crate::cli::repositories::RepoInfo;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub enum UpdateOutcome {
+    Updated(String),
+    UpToDate(String),
+    Dirty,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleStatus {
+    pub name: String,
+    pub path: String,
+    pub cloned: bool,
+    /// Branch, exact tag or abbreviated commit of HEAD.
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub registered_version: String,
+    /// Whether HEAD is the commit of the registered version tag.
+    pub matches_version: bool,
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+    pub dirty_files: usize,
+    /// Version declared in `UPM/package.json`.
+    pub package_version: Option<String>,
+    /// Why the state of the clone could not be read.
+    pub error: Option<String>,
+}
+
+/// Returns the registry entries that are cloned into `dir`, with the path of each clone.
+pub fn find_clones<'a>(repos: &[&'a RepoInfo], dir: &Path) -> Vec<(&'a RepoInfo, PathBuf)> {
+    repos.iter()
+        .map(|repo| (*repo, dir.join(repo.dir_name())))
+        .filter(|(_, path)| path.join(".git").exists())
+        .collect()
+}
+
+/// Fetches the clone and brings it to the registered version: a branch is fast-forwarded to
+/// its upstream, a detached HEAD is moved to the tag of `repo.version`.
+pub fn update(repo: &RepoInfo, path: &Path) -> Result<UpdateOutcome> {
+    if !git::read(path, &["status", "--porcelain"])?.is_empty() {
+        return Ok(UpdateOutcome::Dirty);
+    }
+
+    git::read(path, &["fetch", "--tags", "origin"])?;
+    let head = git::read(path, &["rev-parse", "HEAD"])?;
+
+    if let Ok(branch) = git::read(path, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+        git::read(path, &["merge", "--ff-only", "@{u}"])?;
+        return if git::read(path, &["rev-parse", "HEAD"])? == head {
+            Ok(UpdateOutcome::UpToDate(branch))
+        } else {
+            Ok(UpdateOutcome::Updated(branch))
+        };
+    }
+
+    let tags: Vec<String> = git::read(path, &["for-each-ref", "--format=%(refname)", "refs/tags"])?
+        .lines()
+        .map(String::from)
+        .collect();
+    let tag = repo.find_version_tag(&tags)?;
+    if git::read(path, &["rev-parse", &format!("{}^{{commit}}", tag)])? == head {
+        return Ok(UpdateOutcome::UpToDate(tag));
+    }
+    git::read(path, &["checkout", "--quiet", &tag])?;
+    Ok(UpdateOutcome::Updated(tag))
+}
+
+
+/// The state of the clone of `repo` in `dir`; a git failure is kept in `error`.
+pub fn status(repo: &RepoInfo, dir: &Path) -> ModuleStatus {
+    let path = dir.join(repo.dir_name());
+    let mut status = ModuleStatus {
+        name: repo.name.clone(),
+        path: path.display().to_string(),
+        cloned: path.join(".git").exists(),
+        git_ref: None,
+        registered_version: repo.version.clone(),
+        matches_version: false,
+        ahead: None,
+        behind: None,
+        dirty_files: 0,
+        package_version: None,
+        error: None,
+    };
+    if status.cloned && let Err(e) = read_clone(repo, &path, &mut status) {
+        status.error = Some(e.message().to_string());
+    }
+    status
+}
+
+fn read_clone(repo: &RepoInfo, path: &Path, status: &mut ModuleStatus) -> Result<()> {
+    let head = git::read(path, &["rev-parse", "HEAD"])?;
+    let branch = git::read(path, &["symbolic-ref", "--quiet", "--short", "HEAD"]).ok();
+    status.git_ref = Some(match &branch {
+        Some(branch) => branch.clone(),
+        None => git::read(path, &["describe", "--tags", "--exact-match"])
+            .or_else(|_| git::read(path, &["rev-parse", "--short", "HEAD"]))?,
+    });
+
+    let tags: Vec<String> = git::read(path, &["for-each-ref", "--format=%(refname)", "refs/tags"])?
+        .lines()
+        .map(String::from)
+        .collect();
+    status.matches_version = repo.find_version_tag(&tags)
+        .and_then(|tag| git::read(path, &["rev-parse", &format!("{}^{{commit}}", tag)]))
+        .is_ok_and(|commit| commit == head);
+
+    let counts = branch.as_ref()
+        .and_then(|_| git::read(path, &["rev-list", "--left-right", "--count", "HEAD...@{u}"]).ok());
+    if let Some(counts) = counts {
+        let mut counts = counts.split_whitespace().filter_map(|count| count.parse().ok());
+        status.ahead = counts.next();
+        status.behind = counts.next();
+    }
+
+    status.dirty_files = git::read(path, &["status", "--porcelain"])?.lines().count();
+    status.package_version = fs::read_to_string(path.join("UPM").join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|package| package["version"].as_str().map(String::from));
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+{{#if tests.editor}}
+using NUnit.Framework;
+
+namespace {{display_name}}.Editor.Tests
+{
+	public class SampleEditorTests
+	{
+		[Test]
+		public void SampleTest()
+		{
+			Assert.Pass();
+		}
+	}
+}
+{{/if}}
@@ -0,0 +1,24 @@
+{{#if tests.runtime}}
+using System.Collections;
+using NUnit.Framework;
+using UnityEngine.TestTools;
+
+namespace {{display_name}}.Tests
+{
+	public class SampleRuntimeTests
+	{
+		[Test]
+		public void SampleTest()
+		{
+			Assert.Pass();
+		}
+
+		[UnityTest]
+		public IEnumerator SampleCoroutineTest()
+		{
+			yield return null;
+			Assert.Pass();
+		}
+	}
+}
+{{/if}}
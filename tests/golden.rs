@@ -0,0 +1,150 @@
+//! Golden-file tests of `new`: the generated module must match `tests/golden/<case>.txt`
+//! byte for byte. The lock file is resolved `--offline` from a copy of the registry
+//! documents in `tests/golden/cache/e314`, so a missing document fails the test instead
+//! of being fetched. Run with `UPDATE_GOLDEN=1` to rewrite the golden files from the
+//! current output.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+/// 2023-11-14, the year of the licenses.
+const SOURCE_DATE_EPOCH: &str = "1700000000";
+
+const REGISTRY_CONFIG: &str = "\
+modules:
+  - name: com.e314.exceptions
+    version: 1.1.2
+    url: https://github.com/epishev-m/e314-exceptions.git
+    assemblies:
+      runtime: E314.Exceptions
+      runtime_guid: 4a7c1e0b9d3f4e2a8b6c5d7e9f0a1b2c
+asmdef_references: guid
+dependency_source: registry
+tests: editor
+unity:
+  scoped_registries:
+    - name: OpenUPM
+      url: https://package.openupm.com
+      scopes: [com.e314]
+";
+
+#[test]
+fn new_with_default_settings() {
+    assert_golden("default", None, &["--deps="]);
+}
+
+#[test]
+fn new_with_registry_dependencies() {
+    assert_golden("registry", Some(REGISTRY_CONFIG), &["--deps=com.e314.exceptions",
+        "--keywords", "exceptions,guards", "--author-url", "https://github.com/epishev-m",
+        "--repository", "https://github.com/epishev-m/e314-foo.git", "--min-unity-version", "2022.3.10f1",
+        "--license", "Apache-2.0", "--changelog-url", "https://epishev-m.github.io/e314-foo/changelog",
+        "--samples", "Basic"]);
+}
+
+#[test]
+fn new_twice_gives_the_same_files() {
+    let first = generate("twice-1", None, &["--deps="]);
+    let second = generate("twice-2", None, &["--deps="]);
+    assert_eq!(first, second);
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+/// Runs `new` for the module `Foo` in a fresh directory with `config` as its `e314.yaml`
+/// and returns the snapshot of the module.
+fn generate(case: &str, config: Option<&str>, args: &[&str]) -> String {
+    let dir = env::temp_dir().join(format!("e314-golden-{}-{}", case, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("home")).unwrap();
+    copy_dir(&golden_dir().join("cache"), &dir.join("cache"));
+    if let Some(config) = config {
+        fs::write(dir.join("e314.yaml"), config).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_e314-cli"))
+        .current_dir(&dir)
+        .env("HOME", dir.join("home"))
+        .env_remove("USERPROFILE")
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .env("SOURCE_DATE_EPOCH", SOURCE_DATE_EPOCH)
+        .args(["new", "--name", "Foo", "--version", "1.0.0", "--description", "Golden module",
+            "--author-name", "Maksim Epishev", "--author-email", "epishev.m@mail.ru",
+            "--unity-version", "6000.1.4f1", "--offline"])
+        .args(args)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "new failed:\n{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(!stdout.contains("packages-lock.json - not generated"), "a registry document is missing:\n{}", stdout);
+
+    let snapshot = snapshot(&dir.join("com.e314.foo"));
+    let _ = fs::remove_dir_all(&dir);
+    snapshot
+}
+
+/// The files and folders under `root` in path order, each file as a `==> path <==` header
+/// followed by its content.
+fn snapshot(root: &Path) -> String {
+    let mut paths = Vec::new();
+    collect(root, root, &mut paths);
+    paths.sort();
+
+    let mut snapshot = String::new();
+    for path in paths {
+        let full_path = root.join(&path);
+        let name = path.to_string_lossy().replace('\\', "/");
+        if full_path.is_dir() {
+            snapshot.push_str(&format!("==> {}/ <==\n", name));
+        } else {
+            snapshot.push_str(&format!("==> {} <==\n", name));
+            snapshot.push_str(&fs::read_to_string(&full_path).unwrap());
+        }
+    }
+    snapshot
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let target = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &target);
+        } else {
+            fs::copy(&path, &target).unwrap();
+        }
+    }
+}
+
+fn collect(root: &Path, dir: &Path, paths: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        paths.push(path.strip_prefix(root).unwrap().to_path_buf());
+        if path.is_dir() {
+            collect(root, &path, paths);
+        }
+    }
+}
+
+fn assert_golden(case: &str, config: Option<&str>, args: &[&str]) {
+    let actual = generate(case, config, args);
+    let golden_path = golden_dir().join(format!("{}.txt", case));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden_path).unwrap();
+    let differs = actual.split("==> ").zip(expected.split("==> "))
+        .find(|(actual, expected)| actual != expected);
+    if let Some((actual, expected)) = differs {
+        panic!("{} differs from {}\n--- expected\n{}\n--- actual\n{}",
+               case, golden_path.display(), expected, actual);
+    }
+    assert_eq!(actual, expected, "{} has other files than {}", case, golden_path.display());
+}